@@ -0,0 +1,87 @@
+//! AES-CMAC, from NIST SP 800-38B and RFC 4493.
+//!
+//! CMAC is CBC-MAC with a zero IV, where the last block is xor'd with one of
+//! two subkeys derived from the key, depending on whether it's a whole block.
+//! This makes it safe for messages of varying length, unlike raw CBC-MAC.
+
+use super::{Key, BLOCKSIZE};
+use crate::block::BlockCipher;
+
+/// Multiply by x in GF(2^128), with the big-endian bit order used by CMAC
+/// and SIV.
+pub(super) fn dbl(block: [u8; BLOCKSIZE]) -> [u8; BLOCKSIZE] {
+    let v = u128::from_be_bytes(block);
+    let r = if v >> 127 != 0 { 0x87 } else { 0 };
+    ((v << 1) ^ r).to_be_bytes()
+}
+
+/// Compute the AES-CMAC of `msg`.
+#[must_use]
+pub fn aes_cmac(msg: &[u8], key: &Key) -> [u8; BLOCKSIZE] {
+    let mut l = [0u8; BLOCKSIZE];
+    key.encrypt_block(&mut l);
+    let k1 = dbl(l);
+    let k2 = dbl(k1);
+    let n = msg.len().div_ceil(BLOCKSIZE).max(1);
+    let (init, last) = msg.split_at((n - 1) * BLOCKSIZE);
+    let mut last_block = [0u8; BLOCKSIZE];
+    last_block[..last.len()].copy_from_slice(last);
+    let subkey = if last.len() == BLOCKSIZE {
+        k1
+    } else {
+        last_block[last.len()] = 0x80;
+        k2
+    };
+    let mut x = [0u8; BLOCKSIZE];
+    for block in init.chunks_exact(BLOCKSIZE) {
+        for i in 0..BLOCKSIZE {
+            x[i] ^= block[i];
+        }
+        key.encrypt_block(&mut x);
+    }
+    for i in 0..BLOCKSIZE {
+        x[i] ^= last_block[i] ^ subkey[i];
+    }
+    key.encrypt_block(&mut x);
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aes::sp800_38a::{KEYS, PLAIN};
+    use crate::hex_to_bytes;
+
+    /// RFC 4493 section 4: subkey generation.
+    #[test]
+    fn rfc4493_subkeys() {
+        let l = hex_to_bytes("7df76b0c1ab899b33e42f047b91b546f");
+        let k1 = dbl(l.as_ref().try_into().unwrap());
+        assert_eq!(
+            k1.as_slice(),
+            hex_to_bytes("fbeed618357133667c85e08f7236a8de")
+        );
+        assert_eq!(
+            dbl(k1).as_slice(),
+            hex_to_bytes("f7ddac306ae266ccf90bc11ee46d513b")
+        );
+    }
+
+    /// RFC 4493 section 4: examples 1 to 4.
+    #[test]
+    fn rfc4493_examples() {
+        let key = Key::from_slice(&hex_to_bytes(KEYS[0]));
+        let msg = hex_to_bytes(PLAIN);
+        for (len, expected) in [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ] {
+            assert_eq!(
+                aes_cmac(&msg[..len], &key).as_slice(),
+                hex_to_bytes(expected)
+            );
+        }
+    }
+}
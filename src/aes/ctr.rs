@@ -0,0 +1,158 @@
+//! AES in counter (CTR) mode.
+//!
+//! CTR turns the block cipher into a stream cipher: each block of keystream
+//! is the encryption of a counter block built from a nonce and a block number,
+//! and the keystream is xor'd with the text. Encryption and decryption are the
+//! same operation, and the text need not be a whole number of blocks.
+
+use super::{CounterLayout, Ctr, Key};
+use crate::block::{BlockMode, CtrMode};
+use crate::Result;
+
+/// Encrypt in AES CTR mode, with the block counter starting from 0.
+///
+/// The plaintext can be any length; no padding is needed.
+#[must_use]
+pub fn encrypt_aes_ctr(plain: &[u8], nonce: &[u8], layout: CounterLayout, key: &Key) -> Vec<u8> {
+    let mut buf = plain.to_owned();
+    Ctr::new(key, layout, nonce).apply_keystream(&mut buf);
+    buf
+}
+
+/// Decrypt AES CTR mode, which is the same operation as encryption.
+#[must_use]
+pub fn decrypt_aes_ctr(ct: &[u8], nonce: &[u8], layout: CounterLayout, key: &Key) -> Vec<u8> {
+    encrypt_aes_ctr(ct, nonce, layout, key)
+}
+
+/// Encrypt in AES CTR mode, or return an error if the nonce is the wrong
+/// length for the layout.
+pub fn try_encrypt_aes_ctr(
+    plain: &[u8],
+    nonce: &[u8],
+    layout: CounterLayout,
+    key: &Key,
+) -> Result<Vec<u8>> {
+    CtrMode::new(layout, nonce).try_encrypt(key, plain)
+}
+
+/// Decrypt AES CTR mode, or return an error if the nonce is the wrong
+/// length for the layout.
+pub fn try_decrypt_aes_ctr(
+    ct: &[u8],
+    nonce: &[u8],
+    layout: CounterLayout,
+    key: &Key,
+) -> Result<Vec<u8>> {
+    CtrMode::new(layout, nonce).try_decrypt(key, ct)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::aes::sp800_38a::{KEYS, PLAIN};
+    use crate::{hex_to_bytes, Error};
+
+    /// NIST SP 800-38A F.5.1 and F.5.2: CTR-AES128.
+    #[test]
+    fn nist_ctr_aes128() {
+        let key = Key::from_slice(&hex_to_bytes(KEYS[0]));
+        let nonce = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9fafb");
+        let plain = hex_to_bytes(PLAIN);
+        let expected = hex_to_bytes(
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+            5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+        );
+        let mut buf = plain.to_vec();
+        Ctr::new(&key, CounterLayout::BigEndian32, &nonce)
+            .with_initial_counter(0xfcfdfeff)
+            .apply_keystream(&mut buf);
+        assert_eq!(buf, expected);
+
+        let mut ctr =
+            Ctr::new(&key, CounterLayout::BigEndian32, &nonce).with_initial_counter(0xfcfdfeff);
+        ctr.apply_keystream(&mut buf);
+        assert_eq!(buf, plain);
+    }
+
+    /// NIST SP 800-38A F.5.5: CTR-AES256.
+    #[test]
+    fn nist_ctr_aes256() {
+        let key = Key::from_slice(&hex_to_bytes(KEYS[2]));
+        let nonce = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9fafb");
+        let plain = hex_to_bytes(PLAIN);
+        let expected = hex_to_bytes(
+            "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+            2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6",
+        );
+        let mut buf = plain.to_vec();
+        Ctr::new(&key, CounterLayout::BigEndian32, &nonce)
+            .with_initial_counter(0xfcfdfeff)
+            .apply_keystream(&mut buf);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn wrong_nonce_length_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let key = Key::random_from(&mut rng);
+        assert_eq!(
+            try_encrypt_aes_ctr(b"hi", &[0; 8], CounterLayout::BigEndian32, &key),
+            Err(Error::BadNonceLength(8))
+        );
+        let ct = try_encrypt_aes_ctr(b"hi", &[0; 8], CounterLayout::LittleEndian64, &key).unwrap();
+        assert_eq!(
+            try_decrypt_aes_ctr(&ct, &[0; 8], CounterLayout::LittleEndian64, &key).unwrap(),
+            b"hi"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_aes_ctr(seed: u64, plain: Vec<u8>, nonce: [u8; 8]) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let ct = encrypt_aes_ctr(&plain, &nonce, CounterLayout::LittleEndian64, &key);
+            assert_eq!(ct.len(), plain.len());
+            let ret = decrypt_aes_ctr(&ct, &nonce, CounterLayout::LittleEndian64, &key);
+            assert_eq!(plain, ret);
+        }
+
+        /// Encrypting from an offset gives the same result as the corresponding
+        /// part of encrypting the whole text.
+        #[test]
+        fn seek_matches_whole_text(seed: u64, plain in prop::collection::vec(any::<u8>(), 1..200), start: prop::sample::Index) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let nonce = [7u8; 12];
+            let whole = encrypt_aes_ctr(&plain, &nonce, CounterLayout::BigEndian32, &key);
+            let start = start.index(plain.len());
+            let mut part = plain[start..].to_vec();
+            let mut ctr = Ctr::new(&key, CounterLayout::BigEndian32, &nonce);
+            ctr.seek(start as u64);
+            ctr.apply_keystream(&mut part);
+            assert_eq!(part, &whole[start..]);
+            assert_eq!(ctr.position(), plain.len() as u64);
+        }
+
+        /// Applying the keystream in arbitrary pieces is the same as all at once.
+        #[test]
+        fn apply_in_pieces(seed: u64, plain: Vec<u8>, split: prop::sample::Index) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let nonce = [1u8; 8];
+            let whole = encrypt_aes_ctr(&plain, &nonce, CounterLayout::LittleEndian64, &key);
+            let split = if plain.is_empty() { 0 } else { split.index(plain.len()) };
+            let mut buf = plain.clone();
+            let mut ctr = Ctr::new(&key, CounterLayout::LittleEndian64, &nonce);
+            let (a, b) = buf.split_at_mut(split);
+            ctr.apply_keystream(a);
+            ctr.apply_keystream(b);
+            assert_eq!(buf, whole);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! AES-GCM authenticated encryption, from NIST SP 800-38D.
+//!
+//! The text is encrypted in counter mode, and the ciphertext and additional
+//! authenticated data are hashed with GHASH, a polynomial MAC over GF(2^128).
+//! The sealed output is the ciphertext followed by the tag.
+//!
+//! This doesn't enforce the limits on message length or the number of
+//! invocations per key from the standard.
+
+use super::{CounterLayout, Ctr, Key, BLOCKSIZE};
+use crate::block::BlockCipher;
+use crate::{Error, Result};
+
+/// The full length of a GCM tag in bytes.
+pub const TAG_LEN: usize = 16;
+
+/// The nonce length that GCM is designed around, in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// Tag lengths permitted by SP 800-38D section 5.2.1.2.
+const ALLOWED_TAG_LENS: [usize; 7] = [4, 8, 12, 13, 14, 15, 16];
+
+/// The reduction constant for GCM's bit-reflected representation of
+/// x^128 + x^7 + x^2 + x + 1.
+const R: u128 = 0xe1 << 120;
+
+/// Multiply two elements of GF(2^128) in GCM's bit order, where the most
+/// significant bit of the `u128` is the coefficient of x^0.
+fn gf_mul(x: u128, y: u128) -> u128 {
+    let mut z = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        if x & (1 << i) != 0 {
+            z ^= v;
+        }
+        v = if v & 1 != 0 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+/// GHASH keyed by the hash subkey `h`.
+struct Ghash {
+    h: u128,
+    y: u128,
+}
+
+impl Ghash {
+    fn new(h: u128) -> Ghash {
+        Ghash { h, y: 0 }
+    }
+
+    /// Hash `data`, zero-padding the last block if it's partial.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(BLOCKSIZE) {
+            let mut block = [0u8; BLOCKSIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.y = gf_mul(self.y ^ u128::from_be_bytes(block), self.h);
+        }
+    }
+
+    /// Hash the final block of bit lengths, and return the hash.
+    fn finish(mut self, aad_len: usize, ct_len: usize) -> [u8; BLOCKSIZE] {
+        let lens = ((aad_len as u128 * 8) << 64) | (ct_len as u128 * 8);
+        self.update_padded(&lens.to_be_bytes());
+        self.y.to_be_bytes()
+    }
+}
+
+/// AES-GCM under a key, with a chosen tag length.
+pub struct Gcm<'k> {
+    key: &'k Key,
+    /// The hash subkey: the encryption of the zero block.
+    h: u128,
+    tag_len: usize,
+}
+
+impl<'k> Gcm<'k> {
+    /// Make a GCM instance with full-length 16-byte tags.
+    pub fn new(key: &'k Key) -> Gcm<'k> {
+        let mut h = [0u8; BLOCKSIZE];
+        key.encrypt_block(&mut h);
+        Gcm {
+            key,
+            h: u128::from_be_bytes(h),
+            tag_len: TAG_LEN,
+        }
+    }
+
+    /// Truncate tags to `tag_len` bytes.
+    ///
+    /// Panics unless the length is one allowed by SP 800-38D: 4, 8, or 12
+    /// through 16 bytes.
+    #[must_use]
+    pub fn with_tag_len(self, tag_len: usize) -> Gcm<'k> {
+        self.try_with_tag_len(tag_len)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Truncate tags to `tag_len` bytes, or return an error if SP 800-38D
+    /// doesn't allow that length.
+    pub fn try_with_tag_len(self, tag_len: usize) -> Result<Gcm<'k>> {
+        if ALLOWED_TAG_LENS.contains(&tag_len) {
+            Ok(Gcm { tag_len, ..self })
+        } else {
+            Err(Error::BadTagLength(tag_len))
+        }
+    }
+
+    pub fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    /// Return a counter-mode keystream positioned at the pre-counter block J0.
+    ///
+    /// A 96-bit nonce is used directly with a 32-bit counter starting at 1;
+    /// any other length is hashed to make J0, except that it can't be empty.
+    fn keystream(&self, nonce: &[u8]) -> Result<Ctr<'k, Key>> {
+        if nonce.is_empty() {
+            return Err(Error::BadNonceLength(0));
+        }
+        let j0 = if nonce.len() == NONCE_LEN {
+            let mut j0 = nonce.to_vec();
+            j0.extend_from_slice(&1u32.to_be_bytes());
+            j0
+        } else {
+            let mut ghash = Ghash::new(self.h);
+            ghash.update_padded(nonce);
+            ghash.finish(0, nonce.len()).to_vec()
+        };
+        let counter = u32::from_be_bytes(j0[NONCE_LEN..].try_into().unwrap());
+        Ok(
+            Ctr::new(self.key, CounterLayout::BigEndian32, &j0[..NONCE_LEN])
+                .with_initial_counter(counter.into()),
+        )
+    }
+
+    /// Compute the (possibly truncated) tag over `aad` and `ct`, masking
+    /// with the keystream block for J0.
+    fn tag(&self, ctr: &Ctr<Key>, aad: &[u8], ct: &[u8]) -> Vec<u8> {
+        let mut ghash = Ghash::new(self.h);
+        ghash.update_padded(aad);
+        ghash.update_padded(ct);
+        let s = ghash.finish(aad.len(), ct.len());
+        let mask = ctr.keystream_block(0);
+        s.iter()
+            .zip(mask)
+            .take(self.tag_len)
+            .map(|(a, b)| a ^ b)
+            .collect()
+    }
+
+    /// Encrypt `plain` and authenticate it along with `aad`, returning the
+    /// ciphertext followed by the tag.
+    ///
+    /// The nonce must never be reused with the same key.
+    ///
+    /// Panics if the nonce is empty.
+    #[must_use]
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plain: &[u8]) -> Vec<u8> {
+        self.try_seal(nonce, aad, plain)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Encrypt and authenticate, or return an error if the nonce is empty.
+    pub fn try_seal(&self, nonce: &[u8], aad: &[u8], plain: &[u8]) -> Result<Vec<u8>> {
+        let mut ctr = self.keystream(nonce)?;
+        let mut buf = plain.to_vec();
+        ctr.seek(BLOCKSIZE as u64);
+        ctr.apply_keystream(&mut buf);
+        let tag = self.tag(&ctr, aad, &buf);
+        buf.extend_from_slice(&tag);
+        Ok(buf)
+    }
+
+    /// Check the tag on `sealed` and `aad`, and if it's correct return the
+    /// decrypted plaintext.
+    pub fn open(&self, nonce: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        let mut ctr = self.keystream(nonce)?;
+        if sealed.len() < self.tag_len {
+            return Err(Error::TooShort {
+                len: sealed.len(),
+                min: self.tag_len,
+            });
+        }
+        let (ct, tag) = sealed.split_at(sealed.len() - self.tag_len);
+        let expected = self.tag(&ctr, aad, ct);
+        // Look at every byte, so as not to leak how much of the tag matched.
+        if expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            != 0
+        {
+            return Err(Error::TagMismatch);
+        }
+        let mut buf = ct.to_vec();
+        ctr.seek(BLOCKSIZE as u64);
+        ctr.apply_keystream(&mut buf);
+        Ok(buf)
+    }
+}
+
+/// Encrypt and authenticate with AES-GCM and a full-length tag, returning
+/// the ciphertext followed by the tag.
+///
+/// Panics if the nonce is empty.
+#[must_use]
+pub fn seal_aes_gcm(plain: &[u8], nonce: &[u8], aad: &[u8], key: &Key) -> Vec<u8> {
+    Gcm::new(key).seal(nonce, aad, plain)
+}
+
+/// Encrypt and authenticate with AES-GCM, or return an error if the nonce is
+/// empty.
+pub fn try_seal_aes_gcm(plain: &[u8], nonce: &[u8], aad: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Gcm::new(key).try_seal(nonce, aad, plain)
+}
+
+/// Check and decrypt AES-GCM with a full-length tag.
+pub fn open_aes_gcm(sealed: &[u8], nonce: &[u8], aad: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Gcm::new(key).open(nonce, aad, sealed)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::hex_to_bytes;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PLAIN: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+        1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+    const LONG_NONCE: &str = "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+        c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b";
+
+    /// Check one case from the GCM specification's test vectors, which are
+    /// also used by NIST.
+    fn check(key: &str, nonce: &str, aad: &str, plain: &str, ct: &str, tag: &str) {
+        let key = Key::from_slice(&hex_to_bytes(key));
+        let nonce = hex_to_bytes(nonce);
+        let aad = hex_to_bytes(aad);
+        let plain = hex_to_bytes(plain);
+        let mut expected = hex_to_bytes(ct).to_vec();
+        expected.extend_from_slice(&hex_to_bytes(tag));
+        let sealed = seal_aes_gcm(&plain, &nonce, &aad, &key);
+        assert_eq!(sealed, expected);
+        assert_eq!(open_aes_gcm(&sealed, &nonce, &aad, &key).unwrap(), plain);
+    }
+
+    #[test]
+    fn gf_mul_identity_and_reduction() {
+        // The multiplicative identity is x^0, the top bit.
+        let one = 1 << 127;
+        assert_eq!(gf_mul(one, 0x1234), 0x1234);
+        assert_eq!(gf_mul(0xdead_beef, one), 0xdead_beef);
+        // x * x^127 = x^128, which reduces to x^7 + x^2 + x + 1.
+        assert_eq!(gf_mul(1 << 126, 1), R);
+    }
+
+    #[test]
+    fn test_case_1_empty() {
+        check(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        );
+    }
+
+    #[test]
+    fn test_case_2_one_block() {
+        check(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "00000000000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        );
+    }
+
+    #[test]
+    fn test_case_4_with_aad() {
+        check(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            AAD,
+            PLAIN,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+            21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        );
+    }
+
+    #[test]
+    fn test_case_5_short_nonce() {
+        check(
+            KEY,
+            "cafebabefacedbad",
+            AAD,
+            PLAIN,
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+            73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        );
+    }
+
+    #[test]
+    fn test_case_6_long_nonce() {
+        check(
+            KEY,
+            LONG_NONCE,
+            AAD,
+            PLAIN,
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+            01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        );
+    }
+
+    #[test]
+    fn test_case_16_aes256() {
+        check(
+            &KEY.repeat(2),
+            "cafebabefacedbaddecaf888",
+            AAD,
+            PLAIN,
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+            8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+            "76fc6ece0f4e1768cddf8853bb2d551b",
+        );
+    }
+
+    #[test]
+    fn test_case_18_aes256_long_nonce() {
+        check(
+            &KEY.repeat(2),
+            LONG_NONCE,
+            AAD,
+            PLAIN,
+            "5a8def2f0c9e53f1f75d7853659e2a20eeb2b22aafde6419a058ab4f6f746bf4\
+            0fc0c3b780f244452da3ebf1c5d82cdea2418997200ef82e44ae7e3f",
+            "a44a8266ee1c8eb0c8b5d4cf5ae9f19a",
+        );
+    }
+
+    /// A truncated tag is a prefix of the full tag.
+    #[test]
+    fn truncated_tag() {
+        let key = Key::from_slice(&hex_to_bytes(KEY));
+        let nonce = hex_to_bytes("cafebabefacedbaddecaf888");
+        let gcm = Gcm::new(&key).with_tag_len(12);
+        let sealed = gcm.seal(&nonce, b"aad", b"hello");
+        assert_eq!(sealed.len(), 5 + 12);
+        let full = seal_aes_gcm(b"hello", &nonce, b"aad", &key);
+        assert_eq!(sealed, full[..17]);
+        assert_eq!(gcm.open(&nonce, b"aad", &sealed).unwrap(), b"hello");
+        assert!(open_aes_gcm(&sealed, &nonce, b"aad", &key).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn disallowed_tag_length() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let key = Key::random_from(&mut rng);
+        let _ = Gcm::new(&key).with_tag_len(10);
+    }
+
+    #[test]
+    fn fallible_constructors_and_sealing() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let key = Key::random_from(&mut rng);
+        assert!(matches!(
+            Gcm::new(&key).try_with_tag_len(10),
+            Err(Error::BadTagLength(10))
+        ));
+        assert_eq!(Gcm::new(&key).try_with_tag_len(12).unwrap().tag_len(), 12);
+        assert_eq!(
+            try_seal_aes_gcm(b"hello", b"", b"", &key),
+            Err(Error::BadNonceLength(0))
+        );
+        assert_eq!(
+            try_seal_aes_gcm(b"hello", &[1; 12], b"", &key).unwrap(),
+            seal_aes_gcm(b"hello", &[1; 12], b"", &key)
+        );
+    }
+
+    #[test]
+    fn too_short_to_open() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let key = Key::random_from(&mut rng);
+        assert_eq!(
+            open_aes_gcm(&[0; 15], &[0; 12], b"", &key),
+            Err(Error::TooShort { len: 15, min: 16 })
+        );
+        assert_eq!(
+            open_aes_gcm(&[0; 32], b"", b"", &key),
+            Err(Error::BadNonceLength(0))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_aes_gcm(seed: u64, plain: Vec<u8>, aad: Vec<u8>, nonce in prop::collection::vec(any::<u8>(), 1..40)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let sealed = seal_aes_gcm(&plain, &nonce, &aad, &key);
+            assert_eq!(sealed.len(), plain.len() + TAG_LEN);
+            assert_eq!(open_aes_gcm(&sealed, &nonce, &aad, &key).unwrap(), plain);
+        }
+
+        /// Changing any bit of the ciphertext, tag or AAD makes opening fail.
+        #[test]
+        fn tampering_is_detected(seed: u64, plain: Vec<u8>, aad in prop::collection::vec(any::<u8>(), 1..40), idx: prop::sample::Index, bit in 0..8u8) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let nonce = [7u8; NONCE_LEN];
+            let sealed = seal_aes_gcm(&plain, &nonce, &aad, &key);
+            let mut bad = sealed.clone();
+            bad[idx.index(sealed.len())] ^= 1 << bit;
+            assert_eq!(open_aes_gcm(&bad, &nonce, &aad, &key), Err(Error::TagMismatch));
+            let mut bad_aad = aad.clone();
+            bad_aad[idx.index(aad.len())] ^= 1 << bit;
+            assert!(open_aes_gcm(&sealed, &nonce, &bad_aad, &key).is_err());
+        }
+    }
+}
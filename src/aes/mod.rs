@@ -0,0 +1,403 @@
+//! AES encryption.
+//!
+//! By default the block cipher comes from the `aes` crate; with the `rijndael`
+//! feature it uses our own [crate::rijndael] instead.
+
+#[cfg(not(feature = "rijndael"))]
+use aes::cipher::consts::U16;
+#[cfg(not(feature = "rijndael"))]
+use aes::cipher::generic_array::GenericArray;
+#[cfg(not(feature = "rijndael"))]
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+#[cfg(not(feature = "rijndael"))]
+use aes::{Aes128, Aes192, Aes256};
+use rand::prelude::*;
+
+use crate::block::{BlockCipher, BlockMode, Cbc, Ecb};
+#[cfg(not(feature = "rijndael"))]
+use crate::Error;
+use crate::Result;
+
+mod cmac;
+mod ctr;
+pub mod gcm;
+mod modes;
+mod siv;
+pub mod stream;
+mod xts;
+
+pub use crate::block::{CiphertextStealing, CounterLayout, Ctr};
+pub use cmac::aes_cmac;
+pub use ctr::{decrypt_aes_ctr, encrypt_aes_ctr, try_decrypt_aes_ctr, try_encrypt_aes_ctr};
+pub use gcm::{open_aes_gcm, seal_aes_gcm, try_seal_aes_gcm, Gcm};
+pub use modes::{
+    decrypt_aes_cbc_cts, decrypt_aes_cfb, decrypt_aes_cfb8, decrypt_aes_ofb, decrypt_aes_pcbc,
+    encrypt_aes_cbc_cts, encrypt_aes_cfb, encrypt_aes_cfb8, encrypt_aes_ofb, encrypt_aes_pcbc,
+    try_decrypt_aes_cbc_cts, try_decrypt_aes_cfb, try_decrypt_aes_cfb8, try_decrypt_aes_ofb,
+    try_decrypt_aes_pcbc, try_encrypt_aes_cbc_cts, try_encrypt_aes_cfb, try_encrypt_aes_cfb8,
+    try_encrypt_aes_ofb, try_encrypt_aes_pcbc,
+};
+pub use siv::{open_aes_siv, seal_aes_siv, SivKey};
+pub use xts::{
+    decrypt_aes_xts, encrypt_aes_xts, sector_tweak, try_decrypt_aes_xts, try_encrypt_aes_xts,
+    XtsKey,
+};
+
+pub const BLOCKSIZE: usize = 16;
+
+/// The key lengths accepted by AES, in bytes: AES-128, AES-192 and AES-256.
+pub const KEY_LENGTHS: [usize; 3] = [16, 24, 32];
+
+#[cfg(not(feature = "rijndael"))]
+type Block = GenericArray<u8, U16>;
+
+/// An AES key of any of the supported lengths, ready for use.
+#[derive(Clone)]
+pub struct Key(Cipher);
+
+/// The expanded cipher for one of the AES key sizes, using the `aes` crate.
+#[cfg(not(feature = "rijndael"))]
+#[derive(Clone)]
+enum Cipher {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+/// The expanded cipher, using our own implementation.
+#[cfg(feature = "rijndael")]
+type Cipher = crate::rijndael::Rijndael;
+
+#[cfg(not(feature = "rijndael"))]
+impl Cipher {
+    fn new(key: &[u8]) -> Result<Cipher> {
+        Ok(match key.len() {
+            16 => Cipher::Aes128(Aes128::new(GenericArray::from_slice(key))),
+            24 => Cipher::Aes192(Aes192::new(GenericArray::from_slice(key))),
+            32 => Cipher::Aes256(Aes256::new(GenericArray::from_slice(key))),
+            l => return Err(Error::BadKeyLength(l)),
+        })
+    }
+
+    fn key_len(&self) -> usize {
+        match self {
+            Cipher::Aes128(_) => 16,
+            Cipher::Aes192(_) => 24,
+            Cipher::Aes256(_) => 32,
+        }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block = Block::from_mut_slice(block);
+        match self {
+            Cipher::Aes128(c) => c.encrypt_block(block),
+            Cipher::Aes192(c) => c.encrypt_block(block),
+            Cipher::Aes256(c) => c.encrypt_block(block),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block = Block::from_mut_slice(block);
+        match self {
+            Cipher::Aes128(c) => c.decrypt_block(block),
+            Cipher::Aes192(c) => c.decrypt_block(block),
+            Cipher::Aes256(c) => c.decrypt_block(block),
+        }
+    }
+}
+
+impl Key {
+    /// Make a key from 16, 24 or 32 bytes.
+    ///
+    /// Panics if the key is any other length.
+    pub fn from_slice(key: &[u8]) -> Key {
+        Key::try_from_slice(key).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Make a key from 16, 24 or 32 bytes, selecting AES-128, AES-192 or
+    /// AES-256 respectively.
+    pub fn try_from_slice(key: &[u8]) -> Result<Key> {
+        Ok(Key(Cipher::new(key)?))
+    }
+
+    /// Make a random 128-bit key.
+    pub fn random() -> Key {
+        Key::random_from(&mut thread_rng())
+    }
+
+    /// Make a random 128-bit key from `rng`, so that it can be reproduced
+    /// from the RNG's seed.
+    pub fn random_from<R: RngCore + ?Sized>(rng: &mut R) -> Key {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Key::from_slice(&key)
+    }
+
+    /// The length of the key in bytes.
+    pub fn key_len(&self) -> usize {
+        self.0.key_len()
+    }
+}
+
+impl BlockCipher for Key {
+    fn block_size(&self) -> usize {
+        BLOCKSIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), BLOCKSIZE);
+        self.0.encrypt_block(block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), BLOCKSIZE);
+        self.0.decrypt_block(block)
+    }
+}
+
+/// Make a random IV.
+pub fn random_iv() -> [u8; BLOCKSIZE] {
+    random_iv_from(&mut thread_rng())
+}
+
+/// Make a random IV from `rng`.
+pub fn random_iv_from<R: RngCore + ?Sized>(rng: &mut R) -> [u8; BLOCKSIZE] {
+    let mut iv = [0u8; BLOCKSIZE];
+    rng.fill_bytes(&mut iv);
+    iv
+}
+
+/// Decrypt CBC.
+///
+/// Does not do padding.
+#[must_use]
+pub fn decrypt_aes_cbc(ct: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cbc::new(iv).decrypt(key, ct)
+}
+
+/// Encrypt in AES CBC mode.
+///
+/// The plaintext must be whole blocks; this does not add padding.
+#[must_use]
+pub fn encrypt_aes_cbc(plain: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cbc::new(iv).encrypt(key, plain)
+}
+
+/// Decrypt CBC, or return an error if the IV or ciphertext is the wrong length.
+///
+/// Does not do padding.
+pub fn try_decrypt_aes_cbc(ct: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cbc::new(iv).try_decrypt(key, ct)
+}
+
+/// Encrypt in AES CBC mode, or return an error if the IV or plaintext is
+/// the wrong length.
+pub fn try_encrypt_aes_cbc(plain: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cbc::new(iv).try_encrypt(key, plain)
+}
+
+#[must_use]
+pub fn decrypt_aes_ecb(ct: &[u8], key: &Key) -> Vec<u8> {
+    Ecb.decrypt(key, ct)
+}
+
+#[must_use]
+pub fn encrypt_aes_ecb(plain: &[u8], key: &Key) -> Vec<u8> {
+    Ecb.encrypt(key, plain)
+}
+
+/// Decrypt ECB, or return an error if the ciphertext is not whole blocks.
+pub fn try_decrypt_aes_ecb(ct: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Ecb.try_decrypt(key, ct)
+}
+
+/// Encrypt ECB, or return an error if the plaintext is not whole blocks.
+pub fn try_encrypt_aes_ecb(plain: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Ecb.try_encrypt(key, plain)
+}
+
+/// Examples from NIST SP 800-38A appendix F, shared by the tests of each mode.
+#[cfg(test)]
+mod sp800_38a {
+    /// The plaintext used throughout.
+    pub const PLAIN: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+        30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    /// The AES-128, AES-192 and AES-256 keys.
+    pub const KEYS: [&str; 3] = [
+        "2b7e151628aed2a6abf7158809cf4f3c",
+        "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+    ];
+
+    /// The IV for the CBC, CFB and OFB examples.
+    pub const IV: &str = "000102030405060708090a0b0c0d0e0f";
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::sp800_38a::{IV, KEYS, PLAIN};
+    use super::*;
+    use crate::{hex_to_bytes, pkcs7, Error};
+
+    /// FIPS-197 Appendix C: one block encrypted under each key size.
+    #[test]
+    fn fips197_examples() {
+        let plain = hex_to_bytes("00112233445566778899aabbccddeeff");
+        for (key_len, expected) in [
+            (16, "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            (24, "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            (32, "8ea2b7ca516745bfeafc49904b496089"),
+        ] {
+            let key_bytes: Vec<u8> = (0..key_len as u8).collect();
+            let key = Key::from_slice(&key_bytes);
+            assert_eq!(key.key_len(), key_len);
+            let ct = encrypt_aes_ecb(&plain, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_ecb(&ct, &key), plain);
+        }
+    }
+
+    /// NIST SP 800-38A F.1: ECB-AES128, ECB-AES192, ECB-AES256.
+    #[test]
+    fn sp800_38a_ecb() {
+        let plain = hex_to_bytes(PLAIN);
+        let expected = [
+            "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf\
+            43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4",
+            "bd334f1d6e45f25ff712a214571fa5cc974104846d0ad3ad7734ecb3ecee4eef\
+            ef7afd2270e2e60adce0ba2face6444e9a4b41ba738d6c72fb16691603c18e0e",
+            "f3eed1bdb5d2a03c064b5a7e3db181f8591ccb10d410ed26dc5ba74a31362870\
+            b6ed21b99ca6f4f9f153e7b1beafed1d23304b7a39f9f3ff067d8d8f9e24ecc7",
+        ];
+        for (key, expected) in KEYS.iter().zip(expected) {
+            let key = Key::from_slice(&hex_to_bytes(key));
+            let ct = encrypt_aes_ecb(&plain, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_ecb(&ct, &key), plain);
+        }
+    }
+
+    /// NIST SP 800-38A F.2: CBC-AES128, CBC-AES192, CBC-AES256.
+    #[test]
+    fn sp800_38a_cbc() {
+        let plain = hex_to_bytes(PLAIN);
+        let iv = hex_to_bytes(IV);
+        let expected = [
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+            73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7",
+            "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a\
+            571b242012fb7ae07fa9baac3df102e008b0e27988598881d920a9e64f5615cd",
+            "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+            39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b",
+        ];
+        for (key, expected) in KEYS.iter().zip(expected) {
+            let key = Key::from_slice(&hex_to_bytes(key));
+            let ct = encrypt_aes_cbc(&plain, &iv, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_cbc(&ct, &iv, &key), plain);
+        }
+    }
+
+    #[test]
+    fn bad_key_lengths_are_errors() {
+        for l in [0, 1, 15, 17, 20, 31, 33, 64] {
+            assert_eq!(
+                Key::try_from_slice(&vec![0; l]).err(),
+                Some(Error::BadKeyLength(l))
+            );
+        }
+        for l in KEY_LENGTHS {
+            assert_eq!(Key::try_from_slice(&vec![0; l]).unwrap().key_len(), l);
+        }
+    }
+
+    #[test]
+    fn bad_lengths_are_errors() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let key = Key::random_from(&mut rng);
+        let iv = random_iv_from(&mut rng);
+        assert_eq!(
+            try_encrypt_aes_ecb(&[0; 15], &key),
+            Err(Error::NotBlockAligned {
+                len: 15,
+                block_size: 16
+            })
+        );
+        assert!(try_decrypt_aes_ecb(&[0; 33], &key).is_err());
+        assert!(try_encrypt_aes_cbc(&[0; 17], &iv, &key).is_err());
+        assert_eq!(
+            try_decrypt_aes_cbc(&[0; 32], &iv[..8], &key),
+            Err(Error::BadIvLength {
+                expected: 16,
+                actual: 8
+            })
+        );
+        let ct = try_encrypt_aes_cbc(&[0; 32], &iv, &key).unwrap();
+        assert_eq!(try_decrypt_aes_cbc(&ct, &iv, &key).unwrap(), [0; 32]);
+    }
+
+    proptest! {
+        #[test]
+        fn seeded_keys_and_ivs_repeat(seed: u64) {
+            let mut a = StdRng::seed_from_u64(seed);
+            let mut b = StdRng::seed_from_u64(seed);
+            let (mut x, mut y) = ([0x5a; BLOCKSIZE], [0x5a; BLOCKSIZE]);
+            Key::random_from(&mut a).encrypt_block(&mut x);
+            Key::random_from(&mut b).encrypt_block(&mut y);
+            assert_eq!(x, y);
+            assert_eq!(random_iv_from(&mut a), random_iv_from(&mut b));
+        }
+
+        #[test]
+        fn roundtrip_aes_cbc_any_key_size(
+            seed: u64,
+            key in prop::sample::select(KEY_LENGTHS.to_vec())
+                .prop_flat_map(|l| prop::collection::vec(any::<u8>(), l)),
+            plain: Vec<u8>,
+        ) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::from_slice(&key);
+            let iv = random_iv_from(&mut rng);
+            let padded = pkcs7::pad(&plain, BLOCKSIZE);
+            let ct = encrypt_aes_cbc(&padded, &iv, &key);
+            let ret = decrypt_aes_cbc(&ct, &iv, &key);
+            assert_eq!(pkcs7::unpad(&ret).unwrap(), plain);
+        }
+
+        #[test]
+        fn roundtrip_aes_cbc_padded(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let padded = pkcs7::pad(&plain, BLOCKSIZE);
+            let ct = encrypt_aes_cbc(&padded, &iv, &key);
+            let ret = decrypt_aes_cbc(&ct, &iv, &key);
+            let ret_unpadded = pkcs7::unpad(&ret).unwrap();
+            assert_eq!(plain, ret_unpadded);
+        }
+
+        fn roundtrip_aes_cbc_unpadded(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            if plain.len() % 16 == 0 {
+                let key = Key::random_from(&mut rng);
+                let iv = [0u8; 16];
+                let ct = encrypt_aes_cbc(&plain, &iv, &key);
+                let ret = decrypt_aes_cbc(&ct, &iv, &key);
+                assert_eq!(plain, ret);
+            }
+        }
+
+        #[test]
+        fn roundtrip_aes_ecb(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let padded = pkcs7::pad(&plain, BLOCKSIZE);
+            let ct = encrypt_aes_ecb(&padded, &key);
+            let ret_padded = decrypt_aes_ecb(&ct, &key);
+            assert_eq!(pkcs7::unpad(&ret_padded).expect("unpad"), plain);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! AES in the OFB, CFB, PCBC and CBC ciphertext stealing modes.
+//!
+//! OFB and CFB make the block cipher into a stream cipher, so the text can be
+//! any length. PCBC, like CBC, works on whole blocks and does not add padding.
+//! CBC with ciphertext stealing takes any text of at least one block, without
+//! padding or expansion.
+//!
+//! The `try_` functions return an error, rather than panicking, if the IV or
+//! text is the wrong length.
+
+use super::Key;
+use crate::block::{BlockMode, CbcCts, Cfb, Cfb8, CiphertextStealing, Ofb, Pcbc};
+use crate::Result;
+
+/// Encrypt in AES OFB mode.
+#[must_use]
+pub fn encrypt_aes_ofb(plain: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Ofb::new(iv).encrypt(key, plain)
+}
+
+/// Decrypt AES OFB mode, which is the same operation as encryption.
+#[must_use]
+pub fn decrypt_aes_ofb(ct: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Ofb::new(iv).decrypt(key, ct)
+}
+
+/// Encrypt in AES CFB mode with a 128-bit segment.
+#[must_use]
+pub fn encrypt_aes_cfb(plain: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cfb::new(iv).encrypt(key, plain)
+}
+
+/// Decrypt AES CFB mode with a 128-bit segment.
+#[must_use]
+pub fn decrypt_aes_cfb(ct: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cfb::new(iv).decrypt(key, ct)
+}
+
+/// Encrypt in AES CFB mode with an 8-bit segment.
+#[must_use]
+pub fn encrypt_aes_cfb8(plain: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cfb8::new(iv).encrypt(key, plain)
+}
+
+/// Decrypt AES CFB mode with an 8-bit segment.
+#[must_use]
+pub fn decrypt_aes_cfb8(ct: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Cfb8::new(iv).decrypt(key, ct)
+}
+
+/// Encrypt in AES PCBC mode.
+///
+/// The plaintext must be whole blocks; this does not add padding.
+#[must_use]
+pub fn encrypt_aes_pcbc(plain: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Pcbc::new(iv).encrypt(key, plain)
+}
+
+/// Decrypt AES PCBC mode.
+///
+/// Does not do padding.
+#[must_use]
+pub fn decrypt_aes_pcbc(ct: &[u8], iv: &[u8], key: &Key) -> Vec<u8> {
+    Pcbc::new(iv).decrypt(key, ct)
+}
+
+/// Encrypt in AES CBC mode with ciphertext stealing.
+///
+/// Panics if the plaintext is shorter than one block.
+#[must_use]
+pub fn encrypt_aes_cbc_cts(
+    plain: &[u8],
+    iv: &[u8],
+    variant: CiphertextStealing,
+    key: &Key,
+) -> Vec<u8> {
+    CbcCts::new(iv, variant).encrypt(key, plain)
+}
+
+/// Decrypt AES CBC mode with ciphertext stealing.
+///
+/// Panics if the ciphertext is shorter than one block.
+#[must_use]
+pub fn decrypt_aes_cbc_cts(
+    ct: &[u8],
+    iv: &[u8],
+    variant: CiphertextStealing,
+    key: &Key,
+) -> Vec<u8> {
+    CbcCts::new(iv, variant).decrypt(key, ct)
+}
+
+/// Encrypt in AES OFB mode, or return an error.
+pub fn try_encrypt_aes_ofb(plain: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Ofb::new(iv).try_encrypt(key, plain)
+}
+
+/// Decrypt AES OFB mode, or return an error.
+pub fn try_decrypt_aes_ofb(ct: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Ofb::new(iv).try_decrypt(key, ct)
+}
+
+/// Encrypt in AES CFB-128 mode, or return an error.
+pub fn try_encrypt_aes_cfb(plain: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cfb::new(iv).try_encrypt(key, plain)
+}
+
+/// Decrypt AES CFB-128 mode, or return an error.
+pub fn try_decrypt_aes_cfb(ct: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cfb::new(iv).try_decrypt(key, ct)
+}
+
+/// Encrypt in AES CFB-8 mode, or return an error.
+pub fn try_encrypt_aes_cfb8(plain: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cfb8::new(iv).try_encrypt(key, plain)
+}
+
+/// Decrypt AES CFB-8 mode, or return an error.
+pub fn try_decrypt_aes_cfb8(ct: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Cfb8::new(iv).try_decrypt(key, ct)
+}
+
+/// Encrypt in AES PCBC mode, or return an error.
+pub fn try_encrypt_aes_pcbc(plain: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Pcbc::new(iv).try_encrypt(key, plain)
+}
+
+/// Decrypt AES PCBC mode, or return an error.
+pub fn try_decrypt_aes_pcbc(ct: &[u8], iv: &[u8], key: &Key) -> Result<Vec<u8>> {
+    Pcbc::new(iv).try_decrypt(key, ct)
+}
+
+/// Encrypt in AES CBC mode with ciphertext stealing, or return an error.
+pub fn try_encrypt_aes_cbc_cts(
+    plain: &[u8],
+    iv: &[u8],
+    variant: CiphertextStealing,
+    key: &Key,
+) -> Result<Vec<u8>> {
+    CbcCts::new(iv, variant).try_encrypt(key, plain)
+}
+
+/// Decrypt AES CBC mode with ciphertext stealing, or return an error.
+pub fn try_decrypt_aes_cbc_cts(
+    ct: &[u8],
+    iv: &[u8],
+    variant: CiphertextStealing,
+    key: &Key,
+) -> Result<Vec<u8>> {
+    CbcCts::new(iv, variant).try_decrypt(key, ct)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::aes::sp800_38a::{IV, KEYS, PLAIN};
+    use crate::aes::{random_iv_from, BLOCKSIZE};
+    use crate::{hex_to_bytes, pkcs7, Error};
+
+    /// NIST SP 800-38A F.4: OFB-AES128, OFB-AES192, OFB-AES256.
+    #[test]
+    fn sp800_38a_ofb() {
+        let plain = hex_to_bytes(PLAIN);
+        let iv = hex_to_bytes(IV);
+        let expected = [
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+            9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e",
+            "cdc80d6fddf18cab34c25909c99a4174fcc28b8d4c63837c09e81700c1100401\
+            8d9a9aeac0f6596f559c6d4daf59a5f26d9f200857ca6c3e9cac524bd9acc92a",
+            "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+            71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484",
+        ];
+        for (key, expected) in KEYS.iter().zip(expected) {
+            let key = Key::from_slice(&hex_to_bytes(key));
+            let ct = encrypt_aes_ofb(&plain, &iv, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_ofb(&ct, &iv, &key), plain);
+        }
+    }
+
+    /// NIST SP 800-38A F.3.13-F.3.18: CFB128 for each key size.
+    #[test]
+    fn sp800_38a_cfb128() {
+        let plain = hex_to_bytes(PLAIN);
+        let iv = hex_to_bytes(IV);
+        let expected = [
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+            26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+            "cdc80d6fddf18cab34c25909c99a417467ce7f7f81173621961a2b70171d3d7a\
+            2e1e8a1dd59b88b1c8e60fed1efac4c9c05f9f9ca9834fa042ae8fba584b09ff",
+            "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+            df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471",
+        ];
+        for (key, expected) in KEYS.iter().zip(expected) {
+            let key = Key::from_slice(&hex_to_bytes(key));
+            let ct = encrypt_aes_cfb(&plain, &iv, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_cfb(&ct, &iv, &key), plain);
+        }
+    }
+
+    /// NIST SP 800-38A F.3.7-F.3.12: CFB8 for each key size, on 18 bytes.
+    #[test]
+    fn sp800_38a_cfb8() {
+        let plain = &hex_to_bytes(PLAIN)[..18];
+        let iv = hex_to_bytes(IV);
+        let expected = [
+            "3b79424c9c0dd436bace9e0ed4586a4f32b9",
+            "cda2521ef0a905ca44cd057cbf0d47a0678a",
+            "dc1f1a8520a64db55fcc8ac554844e889700",
+        ];
+        for (key, expected) in KEYS.iter().zip(expected) {
+            let key = Key::from_slice(&hex_to_bytes(key));
+            let ct = encrypt_aes_cfb8(plain, &iv, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(decrypt_aes_cfb8(&ct, &iv, &key), plain);
+        }
+    }
+
+    /// The first block of PCBC is the same as CBC, but later blocks differ.
+    #[test]
+    fn pcbc_first_block_matches_cbc() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let key = Key::random_from(&mut rng);
+        let iv = random_iv_from(&mut rng);
+        let plain = [b'x'; 48];
+        let pcbc = encrypt_aes_pcbc(&plain, &iv, &key);
+        let cbc = crate::aes::encrypt_aes_cbc(&plain, &iv, &key);
+        assert_eq!(pcbc[..16], cbc[..16]);
+        assert_ne!(pcbc[16..], cbc[16..]);
+    }
+
+    /// RFC 3962 appendix B: Kerberos uses CS3 with a zero IV.
+    #[test]
+    fn rfc3962_cs3() {
+        let key = Key::from_slice(&hex_to_bytes("636869636b656e207465726979616b69"));
+        let iv = [0u8; 16];
+        let plain = b"I would like the General Gau's Chicken, please, ";
+        for (len, expected) in [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+            (
+                47,
+                "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+                39312523a78662d5be7fcbcc98ebf5",
+            ),
+            (
+                48,
+                "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+                39312523a78662d5be7fcbcc98ebf5a8",
+            ),
+        ] {
+            let ct = encrypt_aes_cbc_cts(&plain[..len], &iv, CiphertextStealing::Cs3, &key);
+            assert_eq!(ct, hex_to_bytes(expected));
+            assert_eq!(
+                decrypt_aes_cbc_cts(&ct, &iv, CiphertextStealing::Cs3, &key),
+                plain[..len]
+            );
+        }
+    }
+
+    #[test]
+    fn bad_lengths_are_errors() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let key = Key::random_from(&mut rng);
+        let bad_iv = Err(Error::BadIvLength {
+            expected: 16,
+            actual: 12,
+        });
+        assert_eq!(try_encrypt_aes_ofb(b"hello", &[0; 12], &key), bad_iv);
+        assert_eq!(try_decrypt_aes_cfb(b"hello", &[0; 12], &key), bad_iv);
+        assert_eq!(try_decrypt_aes_cfb8(b"hello", &[0; 12], &key), bad_iv);
+        assert_eq!(
+            try_decrypt_aes_pcbc(&[0; 20], &[0; 16], &key),
+            Err(Error::NotBlockAligned {
+                len: 20,
+                block_size: 16
+            })
+        );
+        assert_eq!(
+            try_decrypt_aes_cbc_cts(&[0; 15], &[0; 16], CiphertextStealing::Cs3, &key),
+            Err(Error::TooShort { len: 15, min: 16 })
+        );
+        assert!(try_encrypt_aes_cbc_cts(&[0; 20], &[0; 16], CiphertextStealing::Cs1, &key).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_aes_cbc_cts(seed: u64, plain in prop::collection::vec(any::<u8>(), 16..100)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            for variant in [CiphertextStealing::Cs1, CiphertextStealing::Cs2, CiphertextStealing::Cs3] {
+                let ct = encrypt_aes_cbc_cts(&plain, &iv, variant, &key);
+                assert_eq!(ct.len(), plain.len());
+                assert_eq!(decrypt_aes_cbc_cts(&ct, &iv, variant, &key), plain);
+            }
+        }
+
+        #[test]
+        fn roundtrip_aes_pcbc_padded(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let padded = pkcs7::pad(&plain, BLOCKSIZE);
+            let ct = encrypt_aes_pcbc(&padded, &iv, &key);
+            let ret = decrypt_aes_pcbc(&ct, &iv, &key);
+            assert_eq!(pkcs7::unpad(&ret).unwrap(), plain);
+        }
+
+        #[test]
+        fn roundtrip_aes_ofb(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let ct = encrypt_aes_ofb(&plain, &iv, &key);
+            assert_eq!(ct.len(), plain.len());
+            assert_eq!(decrypt_aes_ofb(&ct, &iv, &key), plain);
+        }
+
+        #[test]
+        fn roundtrip_aes_cfb(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let ct = encrypt_aes_cfb(&plain, &iv, &key);
+            assert_eq!(ct.len(), plain.len());
+            assert_eq!(decrypt_aes_cfb(&ct, &iv, &key), plain);
+        }
+
+        #[test]
+        fn roundtrip_aes_cfb8(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let ct = encrypt_aes_cfb8(&plain, &iv, &key);
+            assert_eq!(ct.len(), plain.len());
+            assert_eq!(decrypt_aes_cfb8(&ct, &iv, &key), plain);
+        }
+
+        /// CFB8 and CFB128 agree on the first byte, which uses the same keystream.
+        #[test]
+        fn cfb8_and_cfb128_first_byte(seed: u64, plain in prop::collection::vec(any::<u8>(), 1..40)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            assert_eq!(
+                encrypt_aes_cfb8(&plain, &iv, &key)[0],
+                encrypt_aes_cfb(&plain, &iv, &key)[0]
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! AES-SIV deterministic authenticated encryption, from RFC 5297.
+//!
+//! The synthetic IV is a CMAC-based PRF (S2V) over the associated data and
+//! the plaintext, and is used both as the authentication tag and as the
+//! counter for encrypting in CTR mode. Reusing a nonce, or using none, only
+//! reveals whether the same message was encrypted twice with the same
+//! associated data, and not which blocks are equal as ECB does.
+//!
+//! A nonce, if used, is passed as the last element of the associated data.
+
+use super::cmac::{aes_cmac, dbl};
+use super::{CounterLayout, Ctr, Key, BLOCKSIZE};
+use crate::{Error, Result};
+
+/// A pair of AES keys for SIV: one for S2V and one for CTR.
+pub struct SivKey {
+    mac: Key,
+    ctr: Key,
+}
+
+impl SivKey {
+    /// Make an SIV key from 32, 48 or 64 bytes.
+    ///
+    /// Panics if the key is any other length.
+    pub fn from_slice(key: &[u8]) -> SivKey {
+        SivKey::try_from_slice(key).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Make an SIV key from 32, 48 or 64 bytes: the first half is the MAC
+    /// key and the second half the CTR key.
+    pub fn try_from_slice(key: &[u8]) -> Result<SivKey> {
+        if ![32, 48, 64].contains(&key.len()) {
+            return Err(Error::BadKeyLength(key.len()));
+        }
+        let (mac, ctr) = key.split_at(key.len() / 2);
+        Ok(SivKey {
+            mac: Key::try_from_slice(mac)?,
+            ctr: Key::try_from_slice(ctr)?,
+        })
+    }
+}
+
+/// The S2V PRF over the associated data strings followed by the plaintext.
+fn s2v(key: &Key, ad: &[&[u8]], plain: &[u8]) -> [u8; BLOCKSIZE] {
+    let mut d = aes_cmac(&[0; BLOCKSIZE], key);
+    for s in ad {
+        let mac = aes_cmac(s, key);
+        d = dbl(d);
+        for i in 0..BLOCKSIZE {
+            d[i] ^= mac[i];
+        }
+    }
+    let mut t = plain.to_owned();
+    if plain.len() >= BLOCKSIZE {
+        let tail = t.len() - BLOCKSIZE;
+        for i in 0..BLOCKSIZE {
+            t[tail + i] ^= d[i];
+        }
+    } else {
+        d = dbl(d);
+        t.push(0x80);
+        t.resize(BLOCKSIZE, 0);
+        for i in 0..BLOCKSIZE {
+            t[i] ^= d[i];
+        }
+    }
+    aes_cmac(&t, key)
+}
+
+/// Apply the CTR keystream started from the synthetic IV.
+fn apply_ctr(v: &[u8; BLOCKSIZE], key: &Key, buf: &mut [u8]) {
+    // Clearing these two bits lets implementations use 64-bit or 32-bit
+    // counter arithmetic; we use a 32-bit counter.
+    let mut q = *v;
+    q[8] &= 0x7f;
+    q[12] &= 0x7f;
+    let counter = u32::from_be_bytes(q[12..].try_into().unwrap());
+    Ctr::new(key, CounterLayout::BigEndian32, &q[..12])
+        .with_initial_counter(counter.into())
+        .apply_keystream(buf);
+}
+
+/// Encrypt and authenticate with AES-SIV, returning the 16-byte synthetic IV
+/// followed by the ciphertext.
+#[must_use]
+pub fn seal_aes_siv(plain: &[u8], ad: &[&[u8]], key: &SivKey) -> Vec<u8> {
+    let v = s2v(&key.mac, ad, plain);
+    let mut out = v.to_vec();
+    out.extend_from_slice(plain);
+    apply_ctr(&v, &key.ctr, &mut out[BLOCKSIZE..]);
+    out
+}
+
+/// Decrypt AES-SIV and check the synthetic IV, returning the plaintext if
+/// it's authentic.
+pub fn open_aes_siv(sealed: &[u8], ad: &[&[u8]], key: &SivKey) -> Result<Vec<u8>> {
+    if sealed.len() < BLOCKSIZE {
+        return Err(Error::TooShort {
+            len: sealed.len(),
+            min: BLOCKSIZE,
+        });
+    }
+    let (v, ct) = sealed.split_at(BLOCKSIZE);
+    let v: [u8; BLOCKSIZE] = v.try_into().unwrap();
+    let mut plain = ct.to_owned();
+    apply_ctr(&v, &key.ctr, &mut plain);
+    let expected = s2v(&key.mac, ad, &plain);
+    // Look at every byte, so as not to leak how much of the tag matched.
+    if expected.iter().zip(v).fold(0, |acc, (a, b)| acc | (a ^ b)) != 0 {
+        return Err(Error::TagMismatch);
+    }
+    Ok(plain)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::hex_to_bytes;
+
+    /// RFC 5297 appendix A.1: deterministic authenticated encryption.
+    #[test]
+    fn rfc5297_deterministic() {
+        let key = SivKey::from_slice(&hex_to_bytes(
+            "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        ));
+        let ad = hex_to_bytes("101112131415161718191a1b1c1d1e1f2021222324252627");
+        let plain = hex_to_bytes("112233445566778899aabbccddee");
+        let sealed = seal_aes_siv(&plain, &[&ad], &key);
+        assert_eq!(
+            sealed,
+            hex_to_bytes("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c")
+        );
+        assert_eq!(open_aes_siv(&sealed, &[&ad], &key).unwrap(), plain);
+    }
+
+    /// RFC 5297 appendix A.2: nonce-based, with two associated data strings
+    /// and a nonce.
+    #[test]
+    fn rfc5297_nonce_based() {
+        let key = SivKey::from_slice(&hex_to_bytes(
+            "7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f",
+        ));
+        let ad1 = hex_to_bytes(
+            "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100",
+        );
+        let ad2 = hex_to_bytes("102030405060708090a0");
+        let nonce = hex_to_bytes("09f911029d74e35bd84156c5635688c0");
+        let plain = b"this is some plaintext to encrypt using SIV-AES";
+        let ad: &[&[u8]] = &[&ad1, &ad2, &nonce];
+        let sealed = seal_aes_siv(plain, ad, &key);
+        assert_eq!(
+            sealed,
+            hex_to_bytes(
+                "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+                dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d"
+            )
+        );
+        assert_eq!(open_aes_siv(&sealed, ad, &key).unwrap(), plain);
+    }
+
+    #[test]
+    fn bad_inputs_are_errors() {
+        assert_eq!(
+            SivKey::try_from_slice(&[0; 16]).err(),
+            Some(Error::BadKeyLength(16))
+        );
+        let key = SivKey::from_slice(&[3; 32]);
+        assert_eq!(
+            open_aes_siv(&[0; 15], &[], &key),
+            Err(Error::TooShort { len: 15, min: 16 })
+        );
+        assert_eq!(open_aes_siv(&[0; 20], &[], &key), Err(Error::TagMismatch));
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_aes_siv(plain: Vec<u8>, ad: Vec<u8>) {
+            let key = SivKey::from_slice(&[9; 64]);
+            let sealed = seal_aes_siv(&plain, &[&ad], &key);
+            assert_eq!(sealed.len(), plain.len() + BLOCKSIZE);
+            assert_eq!(open_aes_siv(&sealed, &[&ad], &key).unwrap(), plain);
+        }
+
+        #[test]
+        fn tampering_is_detected(plain: Vec<u8>, idx: prop::sample::Index, bit in 0..8u8) {
+            let key = SivKey::from_slice(&[9; 48]);
+            let mut sealed = seal_aes_siv(&plain, &[b"ad"], &key);
+            let i = idx.index(sealed.len());
+            sealed[i] ^= 1 << bit;
+            assert_eq!(open_aes_siv(&sealed, &[b"ad"], &key), Err(Error::TagMismatch));
+        }
+    }
+}
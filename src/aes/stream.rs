@@ -0,0 +1,686 @@
+//! Incremental encryption and decryption.
+//!
+//! The functions in [crate::aes] need the whole message in memory, already
+//! padded. The types here instead take data a piece at a time through
+//! [Crypter::update], and add or remove PKCS#7 padding in [Crypter::finalize]
+//! for the modes that need it. [Encryptor] and [Decryptor] work with any
+//! [BlockCipher], chaining blocks with [crate::block::Chain] just as the
+//! one-shot modes do, and have constructors for AES keys.
+//! [CryptWriter] and [CryptReader] wrap them around `std::io` streams, so
+//! arbitrarily large files can be processed in constant memory.
+
+use std::io::{self, Read, Write};
+
+use super::{CiphertextStealing, Ctr, Key};
+use crate::block::{BlockCipher, BlockMode, CbcCts, Chain, Chaining};
+use crate::{pkcs7, Error, Result};
+
+/// Something that transforms a stream of bytes incrementally.
+pub trait Crypter {
+    /// Feed in more input, and return whatever output is ready.
+    ///
+    /// Block modes hold back partial blocks until more input arrives.
+    fn update(&mut self, input: &[u8]) -> Vec<u8>;
+
+    /// Finish the stream and return any remaining output.
+    ///
+    /// Fails if the input was not validly padded, when decrypting.
+    fn finalize(self) -> Result<Vec<u8>>;
+}
+
+/// Of `len` bytes of held input, how many can be processed now.
+///
+/// Ciphertext stealing holds back the last two blocks, at least one byte of
+/// which must be in the last, until the end. Decryption of padded modes holds
+/// back the last whole block, in case it's the padded one.
+fn ready(chaining: Chaining, block_size: usize, len: usize, decrypting: bool) -> usize {
+    let blocks = match chaining {
+        Chaining::CbcCts(_) => len.saturating_sub(block_size + 1) / block_size,
+        _ if decrypting => len.saturating_sub(1) / block_size,
+        _ => len / block_size,
+    };
+    blocks * block_size
+}
+
+/// Encrypts in any of the block modes, adding PKCS#7 padding at the end for
+/// ECB, CBC and PCBC.
+pub struct Encryptor<C: BlockCipher> {
+    cipher: C,
+    chain: Chain,
+    /// Input not yet encrypted: less than one block, or for ciphertext
+    /// stealing, up to two blocks.
+    buf: Vec<u8>,
+}
+
+impl<C: BlockCipher> Encryptor<C> {
+    /// Encrypt with any block cipher, in the given mode, starting from an IV
+    /// which ECB ignores.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn new(cipher: C, chaining: Chaining, iv: &[u8]) -> Encryptor<C> {
+        Encryptor::try_new(cipher, chaining, iv).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [Encryptor::new], but returns an error for a bad IV.
+    pub fn try_new(cipher: C, chaining: Chaining, iv: &[u8]) -> Result<Encryptor<C>> {
+        let bs = cipher.block_size();
+        Ok(Encryptor {
+            chain: Chain::try_new(chaining, iv, bs)?,
+            cipher,
+            buf: Vec::with_capacity(2 * bs),
+        })
+    }
+}
+
+impl Encryptor<Key> {
+    pub fn ecb(key: &Key) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Ecb, &[])
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn cbc(key: &Key, iv: &[u8]) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Cbc, iv)
+    }
+
+    /// Like [Encryptor::cbc], but returns an error for a bad IV.
+    pub fn try_cbc(key: &Key, iv: &[u8]) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::Cbc, iv)
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn pcbc(key: &Key, iv: &[u8]) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Pcbc, iv)
+    }
+
+    /// Like [Encryptor::pcbc], but returns an error for a bad IV.
+    pub fn try_pcbc(key: &Key, iv: &[u8]) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::Pcbc, iv)
+    }
+
+    /// CBC with ciphertext stealing, which fails in [Crypter::finalize] if
+    /// the whole input was shorter than one block.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cbc_cts(key: &Key, iv: &[u8], variant: CiphertextStealing) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::CbcCts(variant), iv)
+    }
+
+    /// Like [Encryptor::cbc_cts], but returns an error for a bad IV.
+    pub fn try_cbc_cts(
+        key: &Key,
+        iv: &[u8],
+        variant: CiphertextStealing,
+    ) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::CbcCts(variant), iv)
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn ofb(key: &Key, iv: &[u8]) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Ofb, iv)
+    }
+
+    /// Like [Encryptor::ofb], but returns an error for a bad IV.
+    pub fn try_ofb(key: &Key, iv: &[u8]) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::Ofb, iv)
+    }
+
+    /// CFB with a 128-bit segment.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cfb(key: &Key, iv: &[u8]) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Cfb, iv)
+    }
+
+    /// Like [Encryptor::cfb], but returns an error for a bad IV.
+    pub fn try_cfb(key: &Key, iv: &[u8]) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::Cfb, iv)
+    }
+
+    /// CFB with an 8-bit segment.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cfb8(key: &Key, iv: &[u8]) -> Encryptor<Key> {
+        Encryptor::new(key.clone(), Chaining::Cfb8, iv)
+    }
+
+    /// Like [Encryptor::cfb8], but returns an error for a bad IV.
+    pub fn try_cfb8(key: &Key, iv: &[u8]) -> Result<Encryptor<Key>> {
+        Encryptor::try_new(key.clone(), Chaining::Cfb8, iv)
+    }
+}
+
+impl<C: BlockCipher> Crypter for Encryptor<C> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        if self.chain.chaining().is_stream() {
+            let mut out = input.to_owned();
+            self.chain.apply_stream(&self.cipher, &mut out, false);
+            return out;
+        }
+        let bs = self.cipher.block_size();
+        let mut held = std::mem::take(&mut self.buf);
+        held.extend_from_slice(input);
+        let n = ready(self.chain.chaining(), bs, held.len(), false);
+        let mut out: Vec<u8> = held.drain(..n).collect();
+        for block in out.chunks_exact_mut(bs) {
+            self.chain.encrypt_block(&self.cipher, block);
+        }
+        self.buf = held;
+        out
+    }
+
+    fn finalize(mut self) -> Result<Vec<u8>> {
+        let chaining = self.chain.chaining();
+        if let Chaining::CbcCts(variant) = chaining {
+            CbcCts::new(self.chain.prev_ct(), variant).try_encrypt(&self.cipher, &self.buf)
+        } else if chaining.is_padded() {
+            let mut last = pkcs7::pad(&self.buf, self.cipher.block_size());
+            debug_assert_eq!(last.len(), self.cipher.block_size());
+            self.chain.encrypt_block(&self.cipher, &mut last);
+            Ok(last)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Decrypts in any of the block modes, removing PKCS#7 padding at the end for
+/// ECB, CBC and PCBC.
+pub struct Decryptor<C: BlockCipher> {
+    cipher: C,
+    chain: Chain,
+    /// Ciphertext not yet decrypted. For the padded modes, once any input has
+    /// been seen this always holds between 1 and a block of bytes, because
+    /// the last block can't be released until we know whether it's the
+    /// padded one.
+    buf: Vec<u8>,
+    /// Total length of ciphertext seen so far.
+    len: usize,
+}
+
+impl<C: BlockCipher> Decryptor<C> {
+    /// Decrypt with any block cipher, in the given mode, starting from an IV
+    /// which ECB ignores.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn new(cipher: C, chaining: Chaining, iv: &[u8]) -> Decryptor<C> {
+        Decryptor::try_new(cipher, chaining, iv).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [Decryptor::new], but returns an error for a bad IV.
+    pub fn try_new(cipher: C, chaining: Chaining, iv: &[u8]) -> Result<Decryptor<C>> {
+        let bs = cipher.block_size();
+        Ok(Decryptor {
+            chain: Chain::try_new(chaining, iv, bs)?,
+            cipher,
+            buf: Vec::with_capacity(3 * bs),
+            len: 0,
+        })
+    }
+}
+
+impl Decryptor<Key> {
+    pub fn ecb(key: &Key) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Ecb, &[])
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn cbc(key: &Key, iv: &[u8]) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Cbc, iv)
+    }
+
+    /// Like [Decryptor::cbc], but returns an error for a bad IV.
+    pub fn try_cbc(key: &Key, iv: &[u8]) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::Cbc, iv)
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn pcbc(key: &Key, iv: &[u8]) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Pcbc, iv)
+    }
+
+    /// Like [Decryptor::pcbc], but returns an error for a bad IV.
+    pub fn try_pcbc(key: &Key, iv: &[u8]) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::Pcbc, iv)
+    }
+
+    /// CBC with ciphertext stealing, which fails in [Crypter::finalize] if
+    /// the whole input was shorter than one block.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cbc_cts(key: &Key, iv: &[u8], variant: CiphertextStealing) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::CbcCts(variant), iv)
+    }
+
+    /// Like [Decryptor::cbc_cts], but returns an error for a bad IV.
+    pub fn try_cbc_cts(
+        key: &Key,
+        iv: &[u8],
+        variant: CiphertextStealing,
+    ) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::CbcCts(variant), iv)
+    }
+
+    /// Panics if the IV is not one block.
+    pub fn ofb(key: &Key, iv: &[u8]) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Ofb, iv)
+    }
+
+    /// Like [Decryptor::ofb], but returns an error for a bad IV.
+    pub fn try_ofb(key: &Key, iv: &[u8]) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::Ofb, iv)
+    }
+
+    /// CFB with a 128-bit segment.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cfb(key: &Key, iv: &[u8]) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Cfb, iv)
+    }
+
+    /// Like [Decryptor::cfb], but returns an error for a bad IV.
+    pub fn try_cfb(key: &Key, iv: &[u8]) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::Cfb, iv)
+    }
+
+    /// CFB with an 8-bit segment.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn cfb8(key: &Key, iv: &[u8]) -> Decryptor<Key> {
+        Decryptor::new(key.clone(), Chaining::Cfb8, iv)
+    }
+
+    /// Like [Decryptor::cfb8], but returns an error for a bad IV.
+    pub fn try_cfb8(key: &Key, iv: &[u8]) -> Result<Decryptor<Key>> {
+        Decryptor::try_new(key.clone(), Chaining::Cfb8, iv)
+    }
+}
+
+impl<C: BlockCipher> Crypter for Decryptor<C> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        self.len += input.len();
+        if self.chain.chaining().is_stream() {
+            let mut out = input.to_owned();
+            self.chain.apply_stream(&self.cipher, &mut out, true);
+            return out;
+        }
+        let bs = self.cipher.block_size();
+        let mut held = std::mem::take(&mut self.buf);
+        held.extend_from_slice(input);
+        let n = ready(self.chain.chaining(), bs, held.len(), true);
+        let mut out: Vec<u8> = held.drain(..n).collect();
+        for block in out.chunks_exact_mut(bs) {
+            self.chain.decrypt_block(&self.cipher, block);
+        }
+        self.buf = held;
+        out
+    }
+
+    fn finalize(mut self) -> Result<Vec<u8>> {
+        let chaining = self.chain.chaining();
+        if let Chaining::CbcCts(variant) = chaining {
+            return CbcCts::new(self.chain.prev_ct(), variant).try_decrypt(&self.cipher, &self.buf);
+        } else if chaining.is_stream() {
+            return Ok(Vec::new());
+        }
+        let bs = self.cipher.block_size();
+        if self.len == 0 {
+            return Err(Error::TooShort { len: 0, min: bs });
+        } else if self.buf.len() != bs {
+            return Err(Error::NotBlockAligned {
+                len: self.len,
+                block_size: bs,
+            });
+        }
+        let mut last = std::mem::take(&mut self.buf);
+        self.chain.decrypt_block(&self.cipher, &mut last);
+        Ok(pkcs7::try_unpad(&last)?.to_owned())
+    }
+}
+
+/// CTR mode needs no padding, so just applies the keystream as data arrives.
+impl<C: BlockCipher + ?Sized> Crypter for Ctr<'_, C> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut out = input.to_owned();
+        self.apply_keystream(&mut out);
+        out
+    }
+
+    fn finalize(self) -> Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Encrypts or decrypts everything written to it, passing the output to an
+/// inner writer.
+///
+/// [CryptWriter::finish] must be called at the end to write the final block.
+pub struct CryptWriter<W: Write, C: Crypter> {
+    inner: W,
+    crypter: C,
+}
+
+impl<W: Write, C: Crypter> CryptWriter<W, C> {
+    pub fn new(inner: W, crypter: C) -> CryptWriter<W, C> {
+        CryptWriter { inner, crypter }
+    }
+
+    /// Write the final output, and return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        let CryptWriter { mut inner, crypter } = self;
+        let last = crypter
+            .finalize()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        inner.write_all(&last)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write, C: Crypter> Write for CryptWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let out = self.crypter.update(buf);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    /// Flush the inner writer. Any partial block is still held back.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads from an inner reader, and returns the encrypted or decrypted data.
+pub struct CryptReader<R: Read, C: Crypter> {
+    inner: R,
+    /// The crypter, until it is finalized at the end of the input.
+    crypter: Option<C>,
+    /// Output produced but not yet returned.
+    pending: Vec<u8>,
+    /// Position of the next unreturned byte in `pending`.
+    pos: usize,
+    /// Space to read input into, kept between refills.
+    input: Vec<u8>,
+}
+
+/// Amount of input read from the inner reader at a time.
+const READ_CHUNK: usize = 64 << 10;
+
+impl<R: Read, C: Crypter> CryptReader<R, C> {
+    pub fn new(inner: R, crypter: C) -> CryptReader<R, C> {
+        CryptReader {
+            inner,
+            crypter: Some(crypter),
+            pending: Vec::new(),
+            pos: 0,
+            input: vec![0; READ_CHUNK],
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, C: Crypter> Read for CryptReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.pending.len() {
+            let Some(crypter) = self.crypter.as_mut() else {
+                return Ok(0);
+            };
+            let n = self.inner.read(&mut self.input)?;
+            self.pending = if n == 0 {
+                self.crypter
+                    .take()
+                    .unwrap()
+                    .finalize()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            } else {
+                crypter.update(&self.input[..n])
+            };
+            self.pos = 0;
+        }
+        let n = std::cmp::min(buf.len(), self.pending.len() - self.pos);
+        buf[..n].copy_from_slice(&self.pending[self.pos..(self.pos + n)]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::aes::{
+        encrypt_aes_cbc, encrypt_aes_cbc_cts, encrypt_aes_cfb, encrypt_aes_cfb8, encrypt_aes_ctr,
+        encrypt_aes_ecb, encrypt_aes_ofb, encrypt_aes_pcbc, random_iv_from, CounterLayout,
+        BLOCKSIZE,
+    };
+    use crate::block::{Cbc, Cfb, Cfb8, Ecb, Ofb, Pcbc};
+    use crate::toy::ToyCipher;
+
+    /// Feed the input to a crypter in pieces of the given sizes, cycling
+    /// through them.
+    fn feed_in_pieces<C: Crypter>(mut crypter: C, input: &[u8], sizes: &[usize]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut rest = input;
+        for sz in sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (a, b) = rest.split_at(std::cmp::min(*sz, rest.len()));
+            out.extend(crypter.update(a));
+            rest = b;
+        }
+        out.extend(crypter.finalize().unwrap());
+        out
+    }
+
+    #[test]
+    fn empty_input_is_one_padding_block() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let key = Key::random_from(&mut rng);
+        let ct = Encryptor::ecb(&key).finalize().unwrap();
+        assert_eq!(ct, encrypt_aes_ecb(&[16; 16], &key));
+        assert_eq!(
+            feed_in_pieces(Decryptor::ecb(&key), &ct, &[1]),
+            Vec::<u8>::new()
+        );
+    }
+
+    #[test]
+    fn truncated_ciphertext_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let key = Key::random_from(&mut rng);
+        let iv = random_iv_from(&mut rng);
+        let ct = encrypt_aes_cbc(&pkcs7::pad(b"hello world", BLOCKSIZE), &iv, &key);
+        let mut dec = Decryptor::cbc(&key, &iv);
+        let _ = dec.update(&ct[..(ct.len() - 1)]);
+        assert!(dec.finalize().is_err());
+        assert!(Decryptor::cbc(&key, &iv).finalize().is_err());
+    }
+
+    #[test]
+    fn bad_padding_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let key = Key::random_from(&mut rng);
+        let ct = encrypt_aes_ecb(&[0u8; 32], &key);
+        let mut dec = Decryptor::ecb(&key);
+        let _ = dec.update(&ct);
+        assert!(dec.finalize().is_err());
+    }
+
+    #[test]
+    fn bad_iv_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let key = Key::random_from(&mut rng);
+        let bad_iv = Error::BadIvLength {
+            expected: 16,
+            actual: 8,
+        };
+        assert_eq!(
+            Encryptor::try_cbc(&key, &[0; 8]).err(),
+            Some(bad_iv.clone())
+        );
+        assert_eq!(
+            Decryptor::try_cbc(&key, &[0; 8]).err(),
+            Some(bad_iv.clone())
+        );
+        assert_eq!(Decryptor::try_cfb8(&key, &[0; 8]).err(), Some(bad_iv));
+        assert!(Encryptor::try_ofb(&key, &[0; 16]).is_ok());
+    }
+
+    #[test]
+    fn short_cts_input_is_an_error() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let key = Key::random_from(&mut rng);
+        let iv = random_iv_from(&mut rng);
+        let mut enc = Encryptor::cbc_cts(&key, &iv, CiphertextStealing::Cs3);
+        assert_eq!(enc.update(b"too short"), Vec::<u8>::new());
+        assert_eq!(enc.finalize(), Err(Error::TooShort { len: 9, min: 16 }));
+    }
+
+    #[test]
+    fn writer_and_reader_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let key = Key::random_from(&mut rng);
+        let iv = random_iv_from(&mut rng);
+        let plain: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = CryptWriter::new(Vec::new(), Encryptor::cbc(&key, &iv));
+        for chunk in plain.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let ct = writer.finish().unwrap();
+        assert_eq!(
+            ct,
+            encrypt_aes_cbc(&pkcs7::pad(&plain, BLOCKSIZE), &iv, &key)
+        );
+
+        let mut reader = CryptReader::new(Cursor::new(ct), Decryptor::cbc(&key, &iv));
+        let mut ret = Vec::new();
+        reader.read_to_end(&mut ret).unwrap();
+        assert_eq!(ret, plain);
+    }
+
+    #[test]
+    fn reader_reports_bad_padding() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let key = Key::random_from(&mut rng);
+        let ct = encrypt_aes_ecb(&[0u8; 48], &key);
+        let mut reader = CryptReader::new(Cursor::new(ct), Decryptor::ecb(&key));
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_ecb_matches_one_shot(seed: u64, plain: Vec<u8>, sizes in prop::collection::vec(1..40usize, 1..5)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let ct = feed_in_pieces(Encryptor::ecb(&key), &plain, &sizes);
+            assert_eq!(&ct, &encrypt_aes_ecb(&pkcs7::pad(&plain, BLOCKSIZE), &key));
+            assert_eq!(feed_in_pieces(Decryptor::ecb(&key), &ct, &sizes), plain);
+        }
+
+        #[test]
+        fn streaming_cbc_matches_one_shot(seed: u64, plain: Vec<u8>, sizes in prop::collection::vec(1..40usize, 1..5)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let ct = feed_in_pieces(Encryptor::cbc(&key, &iv), &plain, &sizes);
+            assert_eq!(&ct, &encrypt_aes_cbc(&pkcs7::pad(&plain, BLOCKSIZE), &iv, &key));
+            assert_eq!(feed_in_pieces(Decryptor::cbc(&key, &iv), &ct, &sizes), plain);
+        }
+
+        #[test]
+        fn streaming_pcbc_and_feedback_modes_match_one_shot(seed: u64, plain: Vec<u8>, sizes in prop::collection::vec(1..40usize, 1..5)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let cases = [
+                (Encryptor::pcbc(&key, &iv), Decryptor::pcbc(&key, &iv),
+                 encrypt_aes_pcbc(&pkcs7::pad(&plain, BLOCKSIZE), &iv, &key)),
+                (Encryptor::ofb(&key, &iv), Decryptor::ofb(&key, &iv), encrypt_aes_ofb(&plain, &iv, &key)),
+                (Encryptor::cfb(&key, &iv), Decryptor::cfb(&key, &iv), encrypt_aes_cfb(&plain, &iv, &key)),
+                (Encryptor::cfb8(&key, &iv), Decryptor::cfb8(&key, &iv), encrypt_aes_cfb8(&plain, &iv, &key)),
+            ];
+            for (enc, dec, expected) in cases {
+                let ct = feed_in_pieces(enc, &plain, &sizes);
+                assert_eq!(&ct, &expected);
+                assert_eq!(feed_in_pieces(dec, &ct, &sizes), plain.clone());
+            }
+        }
+
+        #[test]
+        fn streaming_cts_matches_one_shot(seed: u64, plain in prop::collection::vec(any::<u8>(), 16..100), sizes in prop::collection::vec(1..40usize, 1..5)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            for variant in [CiphertextStealing::Cs1, CiphertextStealing::Cs2, CiphertextStealing::Cs3] {
+                let ct = feed_in_pieces(Encryptor::cbc_cts(&key, &iv, variant), &plain, &sizes);
+                assert_eq!(&ct, &encrypt_aes_cbc_cts(&plain, &iv, variant, &key));
+                assert_eq!(feed_in_pieces(Decryptor::cbc_cts(&key, &iv, variant), &ct, &sizes), plain.clone());
+            }
+        }
+
+        #[test]
+        fn streaming_ctr_matches_one_shot(seed: u64, plain: Vec<u8>, sizes in prop::collection::vec(1..40usize, 1..5)) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let nonce = [3u8; 8];
+            let ctr = Ctr::new(&key, CounterLayout::LittleEndian64, &nonce);
+            let ct = feed_in_pieces(ctr, &plain, &sizes);
+            assert_eq!(ct, encrypt_aes_ctr(&plain, &nonce, CounterLayout::LittleEndian64, &key));
+        }
+
+        /// Any block cipher streams the same as its one-shot modes, here one
+        /// with 8-byte blocks.
+        #[test]
+        fn streaming_toy_cipher_matches_one_shot(key: u64, plain in prop::collection::vec(any::<u8>(), 8..100), sizes in prop::collection::vec(1..20usize, 1..5)) {
+            let toy = ToyCipher::new(key);
+            let iv = [9u8; 8];
+            let padded = pkcs7::pad(&plain, 8);
+            let cases: [(Chaining, Vec<u8>); 9] = [
+                (Chaining::Ecb, Ecb.encrypt(&toy, &padded)),
+                (Chaining::Cbc, Cbc::new(&iv).encrypt(&toy, &padded)),
+                (Chaining::Pcbc, Pcbc::new(&iv).encrypt(&toy, &padded)),
+                (Chaining::Ofb, Ofb::new(&iv).encrypt(&toy, &plain)),
+                (Chaining::Cfb, Cfb::new(&iv).encrypt(&toy, &plain)),
+                (Chaining::Cfb8, Cfb8::new(&iv).encrypt(&toy, &plain)),
+                (Chaining::CbcCts(CiphertextStealing::Cs1), CbcCts::new(&iv, CiphertextStealing::Cs1).encrypt(&toy, &plain)),
+                (Chaining::CbcCts(CiphertextStealing::Cs2), CbcCts::new(&iv, CiphertextStealing::Cs2).encrypt(&toy, &plain)),
+                (Chaining::CbcCts(CiphertextStealing::Cs3), CbcCts::new(&iv, CiphertextStealing::Cs3).encrypt(&toy, &plain)),
+            ];
+            for (chaining, expected) in cases {
+                let ct = feed_in_pieces(Encryptor::new(toy.clone(), chaining, &iv), &plain, &sizes);
+                assert_eq!(&ct, &expected, "{:?}", chaining);
+                assert_eq!(feed_in_pieces(Decryptor::new(toy.clone(), chaining, &iv), &ct, &sizes), plain.clone());
+            }
+        }
+
+        #[test]
+        fn reader_with_small_reads(seed: u64, plain: Vec<u8>, read_size in 1..50usize) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let mut writer = CryptWriter::new(Vec::new(), Encryptor::ecb(&key));
+            writer.write_all(&plain).unwrap();
+            let ct = writer.finish().unwrap();
+            let mut reader = CryptReader::new(Cursor::new(ct), Decryptor::ecb(&key));
+            let mut ret = Vec::new();
+            let mut buf = vec![0; read_size];
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                ret.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(ret, plain);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Bitflipping attacks on unauthenticated CBC and CTR.
+//!
+//! Flipping a bit of CTR ciphertext flips the same bit of the plaintext. In
+//! CBC, flipping a bit of one ciphertext block flips the same bit of the next
+//! plaintext block, but scrambles the block it's in. So an attacker who knows
+//! some of the plaintext can change it to whatever they want, given a
+//! "sacrificial" block before it whose contents don't matter.
+//!
+//! See <https://cryptopals.com/sets/2/challenges/16> and
+//! <https://cryptopals.com/sets/4/challenges/26>.
+
+use std::collections::BTreeSet;
+
+use super::fingerprint::{self, Mode};
+use super::{Counted, Oracle};
+use crate::{Error, Result};
+
+/// Modified CBC ciphertext, with the blocks it ruins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CbcFlip {
+    /// The IV, changed only if the target bytes are in the first block.
+    pub iv: Vec<u8>,
+    pub ct: Vec<u8>,
+    /// The indexes of plaintext blocks that now decrypt to garbage.
+    pub scrambled: Vec<usize>,
+}
+
+/// Change CTR (or any stream cipher) ciphertext so that the plaintext at
+/// `offset`, known to be `known`, decrypts to `wanted` instead.
+///
+/// Panics if `known` and `wanted` are different lengths.
+pub fn ctr_bitflip(ct: &[u8], offset: usize, known: &[u8], wanted: &[u8]) -> Result<Vec<u8>> {
+    assert_eq!(known.len(), wanted.len());
+    check_range(ct, offset, known.len())?;
+    let mut ct = ct.to_owned();
+    for (i, (k, w)) in known.iter().zip(wanted).enumerate() {
+        ct[offset + i] ^= k ^ w;
+    }
+    Ok(ct)
+}
+
+/// Change CBC ciphertext so that the plaintext at `offset`, known to be
+/// `known`, decrypts to `wanted` instead.
+///
+/// The block size is taken from the length of the IV. The change is made to
+/// the block before each target byte, or to the IV for the first block, so
+/// it fails if a target byte would be scrambled.
+///
+/// Panics if `known` and `wanted` are different lengths.
+pub fn cbc_bitflip(
+    ct: &[u8],
+    iv: &[u8],
+    offset: usize,
+    known: &[u8],
+    wanted: &[u8],
+) -> Result<CbcFlip> {
+    assert_eq!(known.len(), wanted.len());
+    let bs = iv.len();
+    if bs == 0 || !ct.len().is_multiple_of(bs) {
+        return Err(Error::NotBlockAligned {
+            len: ct.len(),
+            block_size: bs,
+        });
+    }
+    check_range(ct, offset, known.len())?;
+    let mut iv = iv.to_owned();
+    let mut ct = ct.to_owned();
+    let mut scrambled = BTreeSet::new();
+    for (i, (k, w)) in known.iter().zip(wanted).enumerate() {
+        let pos = offset + i;
+        if k == w {
+            continue;
+        }
+        if pos < bs {
+            iv[pos] ^= k ^ w;
+        } else {
+            ct[pos - bs] ^= k ^ w;
+            scrambled.insert(pos / bs - 1);
+        }
+    }
+    let targets = offset / bs..=(offset + known.len().saturating_sub(1)) / bs;
+    if let Some(b) = scrambled.iter().find(|b| targets.contains(b)) {
+        return Err(Error::AttackFailed(format!(
+            "flipping would scramble target bytes in block {b}"
+        )));
+    }
+    Ok(CbcFlip {
+        iv,
+        ct,
+        scrambled: scrambled.into_iter().collect(),
+    })
+}
+
+fn check_range(ct: &[u8], offset: usize, len: usize) -> Result<()> {
+    if offset + len > ct.len() {
+        return Err(Error::TooShort {
+            len: ct.len(),
+            min: offset + len,
+        });
+    }
+    Ok(())
+}
+
+/// Input for an oracle, laid out so that a placeholder can be bitflipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// The input to send, ending with the placeholder.
+    pub input: Vec<u8>,
+    /// Where the placeholder ends up in the plaintext.
+    pub offset: usize,
+}
+
+/// Work out what to send to `oracle` so that `placeholder` can then be
+/// flipped to something else.
+///
+/// The oracle is fingerprinted to find its mode and the length of the fixed
+/// text before the input. For CBC, the input starts with `filler` bytes to
+/// finish the prefix's last block, then a whole sacrificial block, then the
+/// placeholder at the start of the next block, so that flipping it only
+/// scrambles input. For a stream mode, the input is just the placeholder.
+///
+/// Fails if the placeholder is longer than a block in CBC, or if the oracle
+/// is not deterministic, since then the prefix length can't be found.
+pub fn find_layout<O: Oracle + ?Sized>(
+    oracle: &O,
+    placeholder: &[u8],
+    filler: u8,
+) -> Result<Layout> {
+    let fp = fingerprint::probe(&mut Counted::new(oracle))?;
+    let prefix_len = fp.prefix_len.ok_or_else(|| {
+        Error::AttackFailed("the oracle isn't deterministic, so the prefix is unknown".into())
+    })?;
+    match (fp.mode, fp.block_size) {
+        (Mode::Stream, _) => Ok(Layout {
+            input: placeholder.to_owned(),
+            offset: prefix_len,
+        }),
+        (Mode::Cbc, Some(bs)) if placeholder.len() <= bs => {
+            let align = (bs - prefix_len % bs) % bs;
+            let mut input = vec![filler; align + bs];
+            input.extend_from_slice(placeholder);
+            Ok(Layout {
+                input,
+                offset: prefix_len + align + bs,
+            })
+        }
+        (Mode::Cbc, Some(bs)) => Err(Error::AttackFailed(format!(
+            "a {}-byte placeholder doesn't fit in a {bs}-byte block",
+            placeholder.len()
+        ))),
+        (mode, _) => Err(Error::AttackFailed(format!(
+            "can't bitflip an oracle that seems to use {mode:?}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::aes::{self, random_iv, CounterLayout, Key};
+    use crate::block::{BlockMode, Cbc};
+    use crate::pkcs7;
+    use crate::toy::ToyCipher;
+
+    #[test]
+    fn ctr_flip() {
+        let key = Key::random();
+        let nonce = [7; 8];
+        let plain = b"user=alice;role=guest";
+        let ct = aes::encrypt_aes_ctr(plain, &nonce, CounterLayout::LittleEndian64, &key);
+        let ct = ctr_bitflip(&ct, 16, b"guest", b"admin").unwrap();
+        assert_eq!(
+            aes::decrypt_aes_ctr(&ct, &nonce, CounterLayout::LittleEndian64, &key),
+            b"user=alice;role=admin"
+        );
+        assert_eq!(
+            ctr_bitflip(&ct, 17, b"guest", b"admin"),
+            Err(Error::TooShort { len: 21, min: 22 })
+        );
+    }
+
+    #[test]
+    fn cbc_flip_in_first_block_changes_iv() {
+        let key = Key::random();
+        let iv = random_iv();
+        let plain = pkcs7::pad(b"role=guest", 16);
+        let ct = aes::encrypt_aes_cbc(&plain, &iv, &key);
+        let flip = cbc_bitflip(&ct, &iv, 5, b"guest", b"admin").unwrap();
+        assert_eq!(flip.ct, ct);
+        assert!(flip.scrambled.is_empty());
+        let plain = aes::decrypt_aes_cbc(&flip.ct, &flip.iv, &key);
+        assert_eq!(pkcs7::unpad(&plain).unwrap(), b"role=admin");
+    }
+
+    #[test]
+    fn cbc_flip_across_blocks_fails() {
+        let key = Key::random();
+        let iv = random_iv();
+        let ct = aes::encrypt_aes_cbc(&[b'a'; 48], &iv, &key);
+        assert!(matches!(
+            cbc_bitflip(&ct, &iv, 14, b"aaaa", b"bbbb"),
+            Err(Error::AttackFailed(_))
+        ));
+        assert!(matches!(
+            cbc_bitflip(&ct[..40], &iv, 0, b"a", b"b"),
+            Err(Error::NotBlockAligned { .. })
+        ));
+    }
+
+    #[test]
+    fn stream_layout() {
+        let key = Key::random();
+        let oracle = |input: &[u8]| {
+            let plain = [b"prefix=".as_slice(), input, b";suffix"].concat();
+            aes::encrypt_aes_ctr(&plain, &[0; 8], CounterLayout::LittleEndian64, &key)
+        };
+        let layout = find_layout(&oracle, b"XadminYtrue", b'A').unwrap();
+        assert_eq!(layout.input, b"XadminYtrue");
+        assert_eq!(layout.offset, 7);
+    }
+
+    proptest! {
+        /// Whatever the prefix, the layout leaves it untouched and only
+        /// scrambles the sacrificial block.
+        #[test]
+        fn cbc_layout_and_flip(key: u64, iv: [u8; 8],
+                               prefix in prop::collection::vec(any::<u8>(), 0..30)) {
+            let toy = ToyCipher::new(key);
+            let plain = |input: &[u8]| [&prefix, input, b";suffix"].concat();
+            let oracle = |input: &[u8]| Cbc::new(&iv).encrypt(&toy, &pkcs7::pad(&plain(input), 8));
+            let layout = find_layout(&oracle, b"X:admin", b'A').unwrap();
+            let ct = oracle(&layout.input);
+            let flip = cbc_bitflip(&ct, &iv, layout.offset, b"X:", b";=").unwrap();
+            assert_eq!(flip.iv, iv);
+            assert_eq!(flip.scrambled, [layout.offset / 8 - 1]);
+            let dec = Cbc::new(&iv).decrypt(&toy, &flip.ct);
+            let dec = pkcs7::unpad(&dec).unwrap();
+            assert_eq!(&dec[..prefix.len()], prefix);
+            assert_eq!(&dec[layout.offset..layout.offset + 7], b";=admin");
+        }
+    }
+}
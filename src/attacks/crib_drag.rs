@@ -0,0 +1,292 @@
+//! Crib dragging against a many-time pad.
+//!
+//! When two messages are encrypted with the same keystream, xoring the
+//! ciphertexts cancels the keystream and leaves the xor of the plaintexts.
+//! Xoring a guessed word (a "crib") into that at the right place reveals the
+//! other message there, so sliding the crib along and looking for offsets
+//! where English falls out finds where it fits. Each confirmed guess gives
+//! away some keystream, which decrypts every other message at the same
+//! place, and suggests the next crib.
+//!
+//! This works on messages too short for [`super::fixed_nonce_ctr`] to break
+//! by statistics, but it needs a human, or at least good guesses.
+
+use std::fmt::Write;
+
+use crate::xor::{self, repeating_key_xor};
+use crate::{score_english, Error, Result};
+
+/// Xor two ciphertexts under the same keystream, over the length of the
+/// shorter, giving the xor of their plaintexts.
+pub fn pair_xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let n = a.len().min(b.len());
+    repeating_key_xor(&a[..n], &xor::Key::new(&b[..n]))
+}
+
+/// A place a crib might fit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub offset: usize,
+    /// The [`score_english`] of the revealed text.
+    pub score: u32,
+    /// What each message would hold at this offset, if the crib fits; None
+    /// for messages too short to reach it. The message the crib was placed
+    /// in just holds the crib.
+    pub revealed: Vec<Option<Vec<u8>>>,
+}
+
+/// Slide `crib` across the xor of two plaintexts, from [`pair_xor`], and
+/// return the offsets where the other plaintext looks like English, best
+/// first.
+pub fn drag_pair(pair: &[u8], crib: &[u8]) -> Vec<(usize, u32, Vec<u8>)> {
+    if crib.is_empty() || crib.len() > pair.len() {
+        return Vec::new();
+    }
+    let mut found: Vec<(usize, u32, Vec<u8>)> = (0..=pair.len() - crib.len())
+        .map(|offset| {
+            let revealed =
+                repeating_key_xor(&pair[offset..offset + crib.len()], &xor::Key::new(crib));
+            (offset, score_english(&revealed), revealed)
+        })
+        .filter(|(_, score, _)| *score > 0)
+        .collect();
+    found.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    found
+}
+
+/// A crib-dragging session over messages that share a keystream, keeping
+/// track of the keystream bytes confirmed so far.
+#[derive(Debug, Clone)]
+pub struct CribDrag {
+    cts: Vec<Vec<u8>>,
+    keystream: Vec<Option<u8>>,
+}
+
+impl CribDrag {
+    pub fn new(cts: Vec<Vec<u8>>) -> CribDrag {
+        let len = cts.iter().map(Vec::len).max().unwrap_or(0);
+        CribDrag {
+            cts,
+            keystream: vec![None; len],
+        }
+    }
+
+    pub fn ciphertexts(&self) -> &[Vec<u8>] {
+        &self.cts
+    }
+
+    /// The keystream, with None for bytes not yet known.
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    /// Try `crib` at every offset in message `index`, and return the offsets
+    /// where the other messages look most like English, best first.
+    ///
+    /// Offsets where the crib contradicts keystream that's already locked in
+    /// are skipped.
+    pub fn drag(&self, index: usize, crib: &[u8]) -> Result<Vec<Candidate>> {
+        let ct = self.message(index)?;
+        if crib.is_empty() || crib.len() > ct.len() {
+            return Ok(Vec::new());
+        }
+        let mut found = Vec::new();
+        for offset in 0..=ct.len() - crib.len() {
+            let span = offset..offset + crib.len();
+            let consistent = self.keystream[span.clone()]
+                .iter()
+                .zip(&ct[span.clone()])
+                .zip(crib)
+                .all(|((k, c), p)| k.is_none_or(|k| k == c ^ p));
+            if !consistent {
+                continue;
+            }
+            let mut all = Vec::new();
+            let revealed: Vec<Option<Vec<u8>>> = self
+                .cts
+                .iter()
+                .enumerate()
+                .map(|(j, other)| {
+                    if j == index {
+                        return Some(crib.to_owned());
+                    }
+                    let pair = pair_xor(&ct[span.clone()], other.get(offset..)?);
+                    let text = repeating_key_xor(&pair, &xor::Key::new(crib));
+                    all.extend_from_slice(&text);
+                    Some(text)
+                })
+                .collect();
+            let score = if all.is_empty() {
+                0
+            } else {
+                score_english(&all)
+            };
+            if score > 0 {
+                found.push(Candidate {
+                    offset,
+                    score,
+                    revealed,
+                });
+            }
+        }
+        found.sort_by(|a, b| b.score.cmp(&a.score).then(a.offset.cmp(&b.offset)));
+        Ok(found)
+    }
+
+    /// Confirm that message `index` holds `plain` at `offset`, and lock in
+    /// the keystream there, replacing anything locked before.
+    pub fn lock(&mut self, index: usize, offset: usize, plain: &[u8]) -> Result<()> {
+        let ct = &self.message(index)?[..];
+        let end = offset.checked_add(plain.len());
+        let Some(ct) = end.and_then(|end| ct.get(offset..end)) else {
+            return Err(Error::TooShort {
+                len: ct.len(),
+                min: end.unwrap_or(usize::MAX),
+            });
+        };
+        let key = repeating_key_xor(ct, &xor::Key::new(plain));
+        for (k, b) in self.keystream[offset..].iter_mut().zip(key) {
+            *k = Some(b);
+        }
+        Ok(())
+    }
+
+    /// Forget `len` bytes of keystream starting at `offset`, or as many as
+    /// there are.
+    pub fn unlock(&mut self, offset: usize, len: usize) {
+        let end = offset.saturating_add(len).min(self.keystream.len());
+        for k in self.keystream.get_mut(offset..end).unwrap_or_default() {
+            *k = None;
+        }
+    }
+
+    /// Decrypt message `index` as far as the keystream is known.
+    pub fn plaintext(&self, index: usize) -> Result<Vec<Option<u8>>> {
+        Ok(self
+            .message(index)?
+            .iter()
+            .zip(&self.keystream)
+            .map(|(c, k)| k.map(|k| c ^ k))
+            .collect())
+    }
+
+    /// Show message `index` as text, with `_` for bytes not yet known and
+    /// `.` for unprintable ones.
+    pub fn show(&self, index: usize) -> Result<String> {
+        let mut s = String::new();
+        for b in self.plaintext(index)? {
+            match b {
+                None => s.push('_'),
+                Some(b @ 32..=126) => s.push(b as char),
+                Some(_) => s.push('.'),
+            }
+        }
+        Ok(s)
+    }
+
+    /// Show the keystream as hex, with `??` for bytes not yet known.
+    pub fn show_keystream(&self) -> String {
+        let mut s = String::new();
+        for k in &self.keystream {
+            match k {
+                None => s.push_str("??"),
+                Some(k) => write!(s, "{k:02x}").unwrap(),
+            }
+        }
+        s
+    }
+
+    fn message(&self, index: usize) -> Result<&Vec<u8>> {
+        self.cts.get(index).ok_or_else(|| {
+            Error::AttackFailed(format!(
+                "there's no message {index}, only {}",
+                self.cts.len()
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random::random_bytes;
+
+    const MESSAGES: &[&str] = &[
+        "meet me at the old mill at noon",
+        "bring the documents and a torch",
+        "the password is still swordfish",
+    ];
+
+    fn session() -> (CribDrag, Vec<u8>) {
+        let keystream = random_bytes(40);
+        let cts = MESSAGES
+            .iter()
+            .map(|m| repeating_key_xor(m.as_bytes(), &xor::Key::new(&keystream[..m.len()])))
+            .collect();
+        (CribDrag::new(cts), keystream)
+    }
+
+    #[test]
+    fn pair_xor_cancels_keystream() {
+        let (drag, _) = session();
+        let cts = drag.ciphertexts();
+        let pair = pair_xor(&cts[0], &cts[1]);
+        let plains = pair_xor(MESSAGES[0].as_bytes(), MESSAGES[1].as_bytes());
+        assert_eq!(pair, plains);
+        let found = drag_pair(&pair, b" the ");
+        assert!(found
+            .iter()
+            .any(|(offset, _, text)| *offset == 5 && text == b"me at"));
+    }
+
+    #[test]
+    fn drag_finds_crib_and_lock_decrypts_others() {
+        let (mut drag, keystream) = session();
+        let found = drag.drag(2, b"password").unwrap();
+        let best = found
+            .iter()
+            .find(|c| c.offset == 4)
+            .expect("offset 4 found");
+        assert_eq!(best.revealed[0].as_deref(), Some(&b" me at t"[..]));
+        assert_eq!(best.revealed[1].as_deref(), Some(&b"g the do"[..]));
+        drag.lock(2, 4, b"password").unwrap();
+        assert_eq!(drag.show(0).unwrap(), "____ me at t___________________");
+        assert_eq!(drag.keystream()[4], Some(keystream[4]));
+        assert!(drag.show_keystream().starts_with("????????"));
+
+        // A crib that contradicts what's locked is never offered there.
+        assert!(drag
+            .drag(0, b"xxxxxxxx")
+            .unwrap()
+            .iter()
+            .all(|c| c.offset + 8 <= 4 || c.offset >= 12));
+
+        drag.unlock(0, 100);
+        assert!(drag.keystream().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn lock_past_end_fails() {
+        let (mut drag, _) = session();
+        assert_eq!(
+            drag.lock(0, 28, b"noon!"),
+            Err(Error::TooShort { len: 31, min: 33 })
+        );
+        assert!(drag.lock(5, 0, b"x").is_err());
+        assert_eq!(
+            drag.lock(0, usize::MAX, b"x"),
+            Err(Error::TooShort {
+                len: 31,
+                min: usize::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn unlock_huge_range() {
+        let (mut drag, _) = session();
+        drag.lock(0, 0, b"meet").unwrap();
+        drag.unlock(2, usize::MAX);
+        assert_eq!(drag.show(0).unwrap().get(..4), Some("me__"));
+    }
+}
@@ -0,0 +1,219 @@
+//! ECB cut-and-paste.
+//!
+//! ECB encrypts each block independently, so ciphertext blocks from different
+//! messages under the same key can be spliced together into a new message.
+//! If an attacker can get a server to encrypt text around their input, they
+//! can collect blocks for almost any plaintext they want, including text
+//! the server would refuse to accept as input, by lining up the server's own
+//! text with block boundaries.
+//!
+//! See <https://cryptopals.com/sets/2/challenges/13>.
+
+use std::collections::HashMap;
+
+use super::fingerprint::{self, Mode, Padding};
+use super::{Counted, Oracle};
+use crate::{pkcs7, Error, Result};
+
+/// The byte used to fill out input around the parts that matter.
+const FILLER: u8 = b'A';
+
+/// Forge a ciphertext that decrypts to the oracle's prefix followed by
+/// `target`.
+///
+/// The oracle must encrypt `prefix || input || suffix` with PKCS#7 padding in
+/// ECB mode, where `suffix` is known to the attacker. The prefix doesn't need
+/// to be known, but it's kept as is. The oracle may drop or escape some input
+/// bytes, which are then avoided, so any block of the target holding those
+/// bytes must be made from the suffix.
+///
+/// Each block of the padded target is looked for in what the oracle would
+/// encrypt for inputs of up to three blocks, made of filler and the block's
+/// own bytes, so it can come from the input, the suffix, or both.
+pub fn forge<O: Oracle + ?Sized>(oracle: &O, suffix: &[u8], target: &[u8]) -> Result<Vec<u8>> {
+    let mut oracle = Counted::new(oracle);
+    let fp = fingerprint::probe(&mut oracle)?;
+    let (bs, p) = match (fp.mode, fp.block_size, fp.prefix_len, fp.padding) {
+        (Mode::Ecb, Some(bs), Some(p), Padding::Pkcs7) => (bs, p),
+        _ => {
+            return Err(Error::AttackFailed(format!(
+                "need deterministic ECB with PKCS#7 padding, not {fp:?}"
+            )))
+        }
+    };
+    if fp.suffix_len != Some(suffix.len()) {
+        return Err(Error::AttackFailed(format!(
+            "the oracle appends {:?} bytes, not {}",
+            fp.suffix_len,
+            suffix.len()
+        )));
+    }
+    let mut planner = Planner {
+        oracle,
+        bs,
+        prefix_len: p,
+        suffix,
+        passes: HashMap::new(),
+    };
+    // None stands for a byte of the unknown prefix.
+    let wanted: Vec<Option<u8>> = pkcs7::pad(&[vec![0; p], target.to_vec()].concat(), bs)
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| (i >= p).then_some(b))
+        .collect();
+    let mut forged = Vec::with_capacity(wanted.len());
+    for (j, block) in wanted.chunks(bs).enumerate() {
+        let (input, k) = planner
+            .plan(j, block)
+            .ok_or_else(|| Error::AttackFailed(format!("no input gives block {j}")))?;
+        let ct = planner.oracle.ask(&input);
+        forged.extend_from_slice(&ct[k * bs..(k + 1) * bs]);
+    }
+    Ok(forged)
+}
+
+struct Planner<'a, 's, O: ?Sized> {
+    oracle: Counted<'a, O>,
+    bs: usize,
+    prefix_len: usize,
+    suffix: &'s [u8],
+    /// Whether each byte is known to get through the oracle unchanged.
+    passes: HashMap<u8, bool>,
+}
+
+impl<O: Oracle + ?Sized> Planner<'_, '_, O> {
+    /// Find an input, and the block of its ciphertext, that encrypts to
+    /// `block` at block `j` of the target.
+    fn plan(&mut self, j: usize, block: &[Option<u8>]) -> Option<(Vec<u8>, usize)> {
+        let (bs, p) = (self.bs, self.prefix_len);
+        for n in 0..=(3 * bs) {
+            // Blocks holding prefix bytes can only come from the same place;
+            // other blocks can come from anywhere after the prefix.
+            let ks: Vec<usize> = if j * bs < p {
+                vec![j]
+            } else {
+                (p.div_ceil(bs)..=(p + n + self.suffix.len()) / bs).collect()
+            };
+            for k in ks {
+                let window = k * bs..(k + 1) * bs;
+                // Put the wanted bytes where they land in block k.
+                let input: Vec<u8> = (p..p + n)
+                    .map(|pos| {
+                        if window.contains(&pos) {
+                            block[pos - k * bs].unwrap_or(FILLER)
+                        } else {
+                            FILLER
+                        }
+                    })
+                    .collect();
+                let plain = pkcs7::pad(&[&vec![0; p], &input[..], self.suffix].concat(), bs);
+                let Some(got) = plain.get(window.clone()) else {
+                    continue;
+                };
+                let matches = block
+                    .iter()
+                    .zip(window.clone().zip(got))
+                    .all(|(want, (pos, g))| match want {
+                        None => pos < p,
+                        Some(w) => pos >= p && w == g,
+                    });
+                if matches && self.all_pass(&input) {
+                    return Some((input, k));
+                }
+            }
+        }
+        None
+    }
+
+    fn all_pass(&mut self, input: &[u8]) -> bool {
+        input.iter().all(|&b| self.passes(b))
+    }
+
+    /// Check whether the oracle lets `b` through unchanged: two blocks of it
+    /// should make the output exactly two blocks longer.
+    fn passes(&mut self, b: u8) -> bool {
+        if let Some(&ok) = self.passes.get(&b) {
+            return ok;
+        }
+        let base = self.oracle.ask(&[]).len();
+        let ok = self.oracle.ask(&vec![b; 2 * self.bs]).len() == base + 2 * self.bs;
+        self.passes.insert(b, ok);
+        ok
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aes::{self, Key};
+    use crate::block::{BlockCipher, BlockMode, Ecb};
+    use crate::toy::ToyCipher;
+
+    /// Encrypt `prefix || input || suffix`, dropping `&` and `=` from the
+    /// input.
+    fn oracle<'a>(
+        cipher: &'a dyn BlockCipher,
+        prefix: &'a [u8],
+        suffix: &'a [u8],
+    ) -> impl Fn(&[u8]) -> Vec<u8> + 'a {
+        move |input| {
+            let input: Vec<u8> = input
+                .iter()
+                .copied()
+                .filter(|b| !b"&=".contains(b))
+                .collect();
+            let plain = [prefix, &input, suffix].concat();
+            Ecb.encrypt(cipher, &pkcs7::pad(&plain, cipher.block_size()))
+        }
+    }
+
+    fn decrypt(cipher: &dyn BlockCipher, ct: &[u8]) -> Vec<u8> {
+        pkcs7::unpad(&Ecb.decrypt(cipher, ct)).unwrap().to_owned()
+    }
+
+    #[test]
+    fn forge_admin_profile() {
+        let key = Key::random();
+        let oracle = oracle(&key, b"email=", b"&uid=10&role=user");
+        let target = b"0123456789abc&uid=10&role=admin";
+        let forged = forge(&oracle, b"&uid=10&role=user", target).unwrap();
+        assert_eq!(
+            decrypt(&key, &forged),
+            b"email=0123456789abc&uid=10&role=admin"
+        );
+    }
+
+    /// Metacharacters can come from the middle of the suffix, too.
+    #[test]
+    fn forge_from_suffix_on_toy_cipher() {
+        let toy = ToyCipher::new(99);
+        let oracle = oracle(&toy, b"user=", b";role=guest&x=1");
+        let target = b"bobby;role=guest&x=guest&x=1";
+        let forged = forge(&oracle, b";role=guest&x=1", target).unwrap();
+        assert_eq!(decrypt(&toy, &forged), b"user=bobby;role=guest&x=guest&x=1");
+    }
+
+    #[test]
+    fn impossible_block_fails() {
+        let key = Key::random();
+        let oracle = oracle(&key, b"email=", b"&uid=10&role=user");
+        assert!(matches!(
+            forge(&oracle, b"&uid=10&role=user", b"x&role=admin"),
+            Err(Error::AttackFailed(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_suffix_length_fails() {
+        let key = Key::random();
+        let oracle = oracle(&key, b"", b"suffix");
+        assert!(forge(&oracle, b"suffix!", b"abc").is_err());
+    }
+
+    #[test]
+    fn cbc_fails() {
+        let key = Key::random();
+        let oracle = |input: &[u8]| aes::encrypt_aes_cbc(&pkcs7::pad(input, 16), &[0; 16], &key);
+        assert!(forge(&oracle, b"", b"abc").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Byte-at-a-time ECB decryption.
+//!
+//! If a server encrypts `prefix || attacker input || secret` in ECB mode
+//! under a fixed key, the attacker can line up each unknown byte of the secret
+//! at the end of a block whose other bytes they already know, and match that
+//! block against a dictionary of blocks ending in every possible byte.
+//!
+//! The prefix may be fixed, or may change on every query, as long as the key
+//! and the secret stay the same. The plaintext is assumed to be PKCS#7 padded.
+//!
+//! See <https://cryptopals.com/sets/2/challenges/12> and
+//! <https://cryptopals.com/sets/2/challenges/14>.
+
+use std::collections::HashSet;
+
+use super::fingerprint::{self, Mode};
+use super::{Counted, Oracle};
+use crate::{Error, Result};
+
+/// How many probes to send, per byte of block size, while waiting for a
+/// random prefix to line up, before giving up.
+const ALIGN_ATTEMPTS: usize = 64;
+
+/// What the oracle puts before the attacker's input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    /// The same bytes, of this length, on every query.
+    Fixed(usize),
+    /// Something different on every query.
+    Random,
+}
+
+/// The result of a byte-at-a-time attack, with what was learned about the
+/// oracle along the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// The secret the oracle appends to the input, without padding.
+    pub secret: Vec<u8>,
+    /// The cipher's block size.
+    pub block_size: usize,
+    /// What comes before the input.
+    pub prefix: Prefix,
+    /// The total number of oracle queries.
+    pub queries: usize,
+}
+
+/// Recover the secret that `oracle` appends to its input before encrypting.
+///
+/// The block size, whether the oracle uses ECB, the prefix length, and the
+/// secret length are all discovered by querying the oracle.
+///
+/// A randomly changing prefix is assumed not to end in two equal blocks,
+/// which would be mistaken for the attack's alignment markers.
+pub fn attack<O: Oracle + ?Sized>(oracle: &O) -> Result<Recovered> {
+    let mut oracle = Counted::new(oracle);
+    let fp = fingerprint::probe(&mut oracle)?;
+    let bs = match (fp.mode, fp.block_size) {
+        (Mode::Ecb, Some(bs)) => bs,
+        (mode, _) => {
+            return Err(Error::AttackFailed(format!(
+                "the oracle seems to use {mode:?}, not ECB"
+            )))
+        }
+    };
+    let prefix = match fp.prefix_len {
+        Some(p) => Prefix::Fixed(p),
+        None => Prefix::Random,
+    };
+    let mut secret = Vec::new();
+    // Rotates through the possible alignments of a random prefix.
+    let mut attempt = 0;
+    'bytes: loop {
+        let i = secret.len();
+        // The block-minus-one bytes just before the byte we're after.
+        let mut known = vec![0u8; bs - 1];
+        known.extend_from_slice(&secret);
+        let known = &known[known.len() - (bs - 1)..];
+        let fill = bs - 1 - i % bs;
+        match prefix {
+            Prefix::Fixed(p) => {
+                let filler = (bs - p % bs) % bs;
+                let probe = build_probe(filler, None, known, fill);
+                let ct = oracle.ask(&probe);
+                match next_byte(&ct, (p + filler) / bs, bs, i, fill)? {
+                    Some(b) => secret.push(b),
+                    None => break 'bytes,
+                }
+            }
+            Prefix::Random => {
+                // Never the same as the filler, nor as the first byte of the
+                // dictionary blocks, so that a misaligned probe can't show two
+                // equal marker blocks.
+                let marker = if known[0] == 0xff { 0xfe } else { 0xff };
+                for _ in 0..(ALIGN_ATTEMPTS * bs) {
+                    attempt += 1;
+                    let probe = build_probe(bs + attempt % bs, Some(marker), known, fill);
+                    let ct = oracle.ask(&probe);
+                    if let Some(start) = find_dictionary(&ct, bs) {
+                        match next_byte(&ct, start, bs, i, fill)? {
+                            Some(b) => secret.push(b),
+                            None => break 'bytes,
+                        }
+                        continue 'bytes;
+                    }
+                }
+                return Err(Error::AttackFailed(format!(
+                    "couldn't line up a probe for byte {i}"
+                )));
+            }
+        }
+    }
+    Ok(Recovered {
+        secret,
+        block_size: bs,
+        prefix,
+        queries: oracle.queries,
+    })
+}
+
+/// Build a probe of `filler || marker || marker || dictionary || fill`.
+///
+/// The dictionary is 256 blocks, each of `known` followed by one value of the
+/// last byte. The filler bytes count up from one, so they never match the
+/// marker, nor make a run of equal blocks themselves.
+fn build_probe(filler: usize, marker: Option<u8>, known: &[u8], fill: usize) -> Vec<u8> {
+    let bs = known.len() + 1;
+    let mut probe: Vec<u8> = (1..=filler).map(|b| b as u8).collect();
+    if let Some(m) = marker {
+        probe.resize(probe.len() + 2 * bs, m);
+    }
+    for b in 0..=255 {
+        probe.extend_from_slice(known);
+        probe.push(b);
+    }
+    probe.resize(probe.len() + fill, 0);
+    probe
+}
+
+/// Find the dictionary in the output of a probe with markers, if the probe
+/// was aligned: it's the first 256 distinct blocks after two equal ones.
+fn find_dictionary(ct: &[u8], bs: usize) -> Option<usize> {
+    let blocks: Vec<&[u8]> = ct.chunks(bs).collect();
+    (2..blocks.len().saturating_sub(256)).find(|&j| {
+        let dict = &blocks[j..j + 256];
+        blocks[j - 2] == blocks[j - 1]
+            && !dict.contains(&blocks[j - 1])
+            && dict.iter().collect::<HashSet<_>>().len() == 256
+    })
+}
+
+/// Look up byte `i` of the secret in the output of an aligned probe whose
+/// dictionary starts at block `start`.
+///
+/// Returns None when the secret is shorter than `i + 1` bytes, which the
+/// amount of padding reveals.
+fn next_byte(ct: &[u8], start: usize, bs: usize, i: usize, fill: usize) -> Result<Option<u8>> {
+    let dict_end = (start + 256) * bs;
+    // The fill and the secret, padded.
+    let tail = ct
+        .len()
+        .checked_sub(dict_end)
+        .filter(|&t| t >= bs)
+        .ok_or_else(|| Error::AttackFailed("the output is too short to include padding".into()))?;
+    // The fill puts byte `i` at the end of a block, so the byte exists if
+    // that block isn't all padding.
+    if fill + i + 1 > tail - bs {
+        return Ok(None);
+    }
+    let target = &ct[dict_end + (fill + i) / bs * bs..][..bs];
+    ct[start * bs..dict_end]
+        .chunks(bs)
+        .position(|b| b == target)
+        .map(|b| Some(b as u8))
+        .ok_or_else(|| Error::AttackFailed(format!("byte {i} isn't in the dictionary")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use proptest::prelude::*;
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::aes::{self, random_iv, Key};
+    use crate::block::{BlockCipher, BlockMode, Ecb};
+    use crate::pkcs7;
+    use crate::random::random_bytes_from;
+    use crate::toy::ToyCipher;
+
+    /// Encrypt `prefix || input || secret` in ECB mode.
+    fn ecb(cipher: &dyn BlockCipher, prefix: &[u8], input: &[u8], secret: &[u8]) -> Vec<u8> {
+        let plain = [prefix, input, secret].concat();
+        Ecb.encrypt(cipher, &pkcs7::pad(&plain, cipher.block_size()))
+    }
+
+    #[test]
+    fn no_prefix() {
+        let key = Key::random();
+        let secret = b"attack at dawn, with a secret longer than one block";
+        let recovered = attack(&|input: &[u8]| ecb(&key, &[], input, secret)).unwrap();
+        assert_eq!(recovered.secret, secret);
+        assert_eq!(recovered.block_size, 16);
+        assert_eq!(recovered.prefix, Prefix::Fixed(0));
+    }
+
+    /// A secret ending in what looks like padding is still recovered exactly.
+    #[test]
+    fn secret_like_padding() {
+        let key = Key::random();
+        let secret = b"\x01\x02\x02\x01";
+        let oracle = |input: &[u8]| ecb(&key, &[], input, secret);
+        assert_eq!(attack(&oracle).unwrap().secret, secret);
+    }
+
+    #[test]
+    fn cbc_is_not_attacked() {
+        let key = Key::random();
+        let oracle =
+            |input: &[u8]| aes::encrypt_aes_cbc(&pkcs7::pad(input, 16), &random_iv(), &key);
+        assert!(matches!(attack(&oracle), Err(Error::AttackFailed(_))));
+    }
+
+    #[test]
+    fn stream_cipher_is_not_attacked() {
+        let oracle = |input: &[u8]| input.iter().map(|b| b ^ 0x55).collect::<Vec<u8>>();
+        assert!(matches!(attack(&oracle), Err(Error::AttackFailed(_))));
+    }
+
+    proptest! {
+        #[test]
+        fn fixed_prefix(key: u64, prefix in prop::collection::vec(any::<u8>(), 0..40),
+                        secret in prop::collection::vec(any::<u8>(), 0..40)) {
+            let toy = ToyCipher::new(key);
+            let recovered = attack(&|input: &[u8]| ecb(&toy, &prefix, input, &secret)).unwrap();
+            assert_eq!(recovered.secret, secret);
+            assert_eq!(recovered.block_size, 8);
+            assert_eq!(recovered.prefix, Prefix::Fixed(prefix.len()));
+        }
+    }
+
+    proptest! {
+        // Each case takes thousands of queries.
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn random_prefix(key: u64, seed: u64, secret in prop::collection::vec(any::<u8>(), 0..40)) {
+            let toy = ToyCipher::new(key);
+            // The oracle's own randomness comes from the seed too, so a
+            // failure can be replayed.
+            let rng = RefCell::new(StdRng::seed_from_u64(seed));
+            let oracle = |input: &[u8]| {
+                let rng = &mut *rng.borrow_mut();
+                let len = rng.gen_range(0..40);
+                let prefix = random_bytes_from(rng, len);
+                ecb(&toy, &prefix, input, &secret)
+            };
+            let recovered = attack(&oracle).unwrap();
+            assert_eq!(recovered.secret, secret);
+            assert_eq!(recovered.prefix, Prefix::Random);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Find out how an encryption oracle works by querying it.
+//!
+//! This generalizes <https://cryptopals.com/sets/2/challenges/11>, which only
+//! asks whether an oracle uses ECB or CBC.
+
+use super::{Counted, Oracle};
+use crate::{Error, Result};
+
+/// The largest block size that will be recognized.
+pub const MAX_BLOCK_SIZE: usize = 64;
+
+/// How the oracle seems to use its cipher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// A block cipher where equal plaintext blocks give equal ciphertext
+    /// blocks.
+    Ecb,
+    /// A block cipher where equal plaintext blocks don't show: CBC, or
+    /// another chained mode such as PCBC.
+    Cbc,
+    /// The output grows by one byte for each byte of input, as in CTR, OFB or
+    /// CFB.
+    Stream,
+}
+
+/// How the oracle pads its plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Not padded, as for a stream mode.
+    None,
+    /// PKCS#7 padded.
+    Pkcs7,
+    /// Padded to a whole number of blocks in some way that couldn't be
+    /// checked, because the mode is not deterministic ECB.
+    Unknown,
+}
+
+/// What was learned about an oracle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// The cipher's block size, or None for a stream mode.
+    pub block_size: Option<usize>,
+    /// True if the same input always gives the same output.
+    pub deterministic: bool,
+    pub mode: Mode,
+    /// The length of the text before the input, if it's fixed.
+    pub prefix_len: Option<usize>,
+    /// The length of the text after the input, if it can be found.
+    pub suffix_len: Option<usize>,
+    pub padding: Padding,
+    /// The number of oracle queries.
+    pub queries: usize,
+}
+
+/// Fingerprint an oracle.
+pub fn fingerprint<O: Oracle + ?Sized>(oracle: &O) -> Result<Fingerprint> {
+    probe(&mut Counted::new(oracle))
+}
+
+/// Fingerprint an oracle, counting queries into `oracle`.
+pub(crate) fn probe<O: Oracle + ?Sized>(oracle: &mut Counted<O>) -> Result<Fingerprint> {
+    // Enough lengths to cross at least two block boundaries.
+    let input = [0u8; 2 * MAX_BLOCK_SIZE];
+    let lengths: Vec<usize> = (0..=input.len())
+        .map(|n| oracle.ask(&input[..n]).len())
+        .collect();
+    let block_size = match lengths.iter().fold(0, |g, &l| gcd(g, l)) {
+        1 => None,
+        bs @ 2..=MAX_BLOCK_SIZE => Some(bs),
+        bs => {
+            return Err(Error::AttackFailed(format!(
+                "output lengths suggest a block size of {bs}"
+            )))
+        }
+    };
+    let first = oracle.ask(&[]);
+    // A random prefix or IV could by chance be the same twice, but not four
+    // times.
+    let deterministic = (0..3).all(|_| oracle.ask(&[]) == first);
+    let mode = match block_size {
+        None => Mode::Stream,
+        Some(bs) if has_repeated_blocks(oracle, bs) => Mode::Ecb,
+        Some(_) => Mode::Cbc,
+    };
+    let bs = block_size.unwrap_or(1);
+    let prefix_len = if deterministic {
+        Some(prefix_len(oracle, bs)?)
+    } else {
+        None
+    };
+    // The shortest input that makes the output grow.
+    let jump = lengths.iter().position(|&l| l > lengths[0]);
+    let padding = match (mode, prefix_len, jump) {
+        (Mode::Stream, _, _) => Padding::None,
+        (Mode::Ecb, Some(p), Some(jump)) if is_pkcs7(oracle, bs, p, jump) => Padding::Pkcs7,
+        _ => Padding::Unknown,
+    };
+    let suffix_len = match (padding, prefix_len, jump) {
+        (Padding::None, Some(p), _) => lengths[0].checked_sub(p),
+        // The prefix, the jump, and the suffix exactly fill the blocks of the
+        // output for an empty input.
+        (Padding::Pkcs7, Some(p), Some(jump)) => lengths[0].checked_sub(p + jump),
+        _ => None,
+    };
+    Ok(Fingerprint {
+        block_size,
+        deterministic,
+        mode,
+        prefix_len,
+        suffix_len,
+        padding,
+        queries: oracle.queries,
+    })
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Three blocks of the same input always include two aligned blocks, which
+/// ECB encrypts the same.
+fn has_repeated_blocks<O: Oracle + ?Sized>(oracle: &mut Counted<O>, bs: usize) -> bool {
+    let ct = oracle.ask(&vec![0; 3 * bs]);
+    let blocks: Vec<&[u8]> = ct.chunks(bs).collect();
+    blocks.windows(2).any(|w| w[0] == w[1])
+}
+
+/// Find the length of a fixed prefix, for a deterministic oracle.
+///
+/// Changing the first input byte changes the block holding the end of the
+/// prefix. Then, filler is added before the changed byte until that block
+/// stays the same, which happens once the filler completes it.
+fn prefix_len<O: Oracle + ?Sized>(oracle: &mut Counted<O>, bs: usize) -> Result<usize> {
+    let a = oracle.ask(&[0]);
+    let b = oracle.ask(&[1]);
+    let block = a
+        .chunks(bs)
+        .zip(b.chunks(bs))
+        .position(|(x, y)| x != y)
+        .ok_or_else(|| Error::AttackFailed("the input makes no difference".into()))?;
+    let range = block * bs..(block + 1) * bs;
+    for k in 1..=bs {
+        let mut input = vec![0; k + 1];
+        let a = oracle.ask(&input);
+        input[k] = 1;
+        let b = oracle.ask(&input);
+        if a[range.clone()] == b[range.clone()] {
+            return Ok((block + 1) * bs - k);
+        }
+    }
+    Err(Error::AttackFailed(
+        "couldn't find the end of the prefix".into(),
+    ))
+}
+
+/// For deterministic ECB, check that when the text fills the last block, the
+/// oracle adds a block that encrypts the same as a whole block of PKCS#7
+/// padding.
+fn is_pkcs7<O: Oracle + ?Sized>(
+    oracle: &mut Counted<O>,
+    bs: usize,
+    prefix: usize,
+    jump: usize,
+) -> bool {
+    let filled = oracle.ask(&vec![0; jump]);
+    let filler = (bs - prefix % bs) % bs;
+    let mut input = vec![0; filler];
+    input.resize(filler + bs, bs as u8);
+    let start = prefix + filler;
+    let padding = oracle.ask(&input);
+    filled.len() >= bs && padding.get(start..start + bs) == Some(&filled[filled.len() - bs..])
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::aes::{self, random_iv, CounterLayout, Key};
+    use crate::block::{BlockCipher, BlockMode, Ecb};
+    use crate::pkcs7;
+    use crate::random::random_bytes;
+    use crate::toy::ToyCipher;
+
+    /// Encrypt `prefix || input || suffix` in ECB mode.
+    fn ecb(cipher: &dyn BlockCipher, prefix: &[u8], input: &[u8], suffix: &[u8]) -> Vec<u8> {
+        let plain = [prefix, input, suffix].concat();
+        Ecb.encrypt(cipher, &pkcs7::pad(&plain, cipher.block_size()))
+    }
+
+    #[test]
+    fn aes_ecb() {
+        let key = Key::random();
+        let fp = fingerprint(&|input: &[u8]| ecb(&key, b"prefix", input, b"suffix!")).unwrap();
+        assert_eq!(fp.block_size, Some(16));
+        assert!(fp.deterministic);
+        assert_eq!(fp.mode, Mode::Ecb);
+        assert_eq!(fp.prefix_len, Some(6));
+        assert_eq!(fp.suffix_len, Some(7));
+        assert_eq!(fp.padding, Padding::Pkcs7);
+        assert!(fp.queries > 0);
+    }
+
+    #[test]
+    fn ecb_with_random_prefix() {
+        let key = Key::random();
+        let oracle = |input: &[u8]| {
+            let prefix = random_bytes(thread_rng().gen_range(5..=10));
+            ecb(&key, &prefix, input, b"suffix")
+        };
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.block_size, Some(16));
+        assert!(!fp.deterministic);
+        assert_eq!(fp.mode, Mode::Ecb);
+        assert_eq!(fp.prefix_len, None);
+        assert_eq!(fp.suffix_len, None);
+    }
+
+    /// Without padding, ECB's last block doesn't match a block of padding.
+    #[test]
+    fn ecb_with_zero_padding() {
+        let key = Key::random();
+        let oracle = |input: &[u8]| {
+            let mut plain = [b"abc", input].concat();
+            plain.resize(plain.len().next_multiple_of(16), 0);
+            aes::encrypt_aes_ecb(&plain, &key)
+        };
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.mode, Mode::Ecb);
+        assert_eq!(fp.prefix_len, Some(3));
+        assert_eq!(fp.padding, Padding::Unknown);
+        assert_eq!(fp.suffix_len, None);
+    }
+
+    #[test]
+    fn cbc_with_fixed_iv() {
+        let key = Key::random();
+        let iv = random_iv();
+        let oracle = |input: &[u8]| {
+            let plain = [b"0123456789abcdefXYZ", input].concat();
+            aes::encrypt_aes_cbc(&pkcs7::pad(&plain, 16), &iv, &key)
+        };
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.block_size, Some(16));
+        assert!(fp.deterministic);
+        assert_eq!(fp.mode, Mode::Cbc);
+        assert_eq!(fp.prefix_len, Some(19));
+        assert_eq!(fp.padding, Padding::Unknown);
+    }
+
+    #[test]
+    fn cbc_with_random_iv() {
+        let key = Key::random();
+        let oracle =
+            |input: &[u8]| aes::encrypt_aes_cbc(&pkcs7::pad(input, 16), &random_iv(), &key);
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.block_size, Some(16));
+        assert!(!fp.deterministic);
+        assert_eq!(fp.mode, Mode::Cbc);
+        assert_eq!(fp.prefix_len, None);
+    }
+
+    #[test]
+    fn ctr_with_fixed_nonce() {
+        let key = Key::random();
+        let oracle = |input: &[u8]| {
+            let plain = [b"comment1=cooking", input, b";comment2=bacon"].concat();
+            aes::encrypt_aes_ctr(&plain, &[0; 8], CounterLayout::LittleEndian64, &key)
+        };
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.block_size, None);
+        assert!(fp.deterministic);
+        assert_eq!(fp.mode, Mode::Stream);
+        assert_eq!(fp.prefix_len, Some(16));
+        assert_eq!(fp.suffix_len, Some(15));
+        assert_eq!(fp.padding, Padding::None);
+    }
+
+    #[test]
+    fn constant_output_is_an_error() {
+        let oracle = |_: &[u8]| vec![0; 128];
+        assert!(fingerprint(&oracle).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn toy_ecb(key: u64, prefix in prop::collection::vec(any::<u8>(), 0..40),
+                   suffix in prop::collection::vec(any::<u8>(), 0..40)) {
+            let toy = ToyCipher::new(key);
+            let fp = fingerprint(&|input: &[u8]| ecb(&toy, &prefix, input, &suffix)).unwrap();
+            assert_eq!(fp.block_size, Some(8));
+            assert_eq!(fp.mode, Mode::Ecb);
+            assert_eq!(fp.prefix_len, Some(prefix.len()));
+            assert_eq!(fp.suffix_len, Some(suffix.len()));
+            assert_eq!(fp.padding, Padding::Pkcs7);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Break CTR with a reused nonce, by statistics.
+//!
+//! CTR under a fixed key and nonce produces the same keystream for every
+//! message, so lining the ciphertexts up gives one column per keystream byte,
+//! each of which is a single-byte xor of whatever the messages hold at that
+//! position. If they're English, each column can be broken on its own by
+//! letter frequencies, just like repeating-key xor.
+//!
+//! See <https://cryptopals.com/sets/3/challenges/19> and
+//! <https://cryptopals.com/sets/3/challenges/20>.
+
+use crate::xor::guess_single_byte_key;
+use crate::{Error, Result};
+
+/// How many ciphertexts must reach a column for it to be worth guessing.
+pub const MIN_COLUMN: usize = 8;
+
+/// The keystream and plaintexts recovered from fixed-nonce ciphertexts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// The keystream, as far as it could be recovered.
+    pub keystream: Vec<u8>,
+    /// The length of the shortest ciphertext, which every message covers.
+    pub common_len: usize,
+    /// Each ciphertext decrypted with the keystream, cut off where the
+    /// keystream ends.
+    pub plaintexts: Vec<Vec<u8>>,
+}
+
+/// Recover the keystream shared by `cts`, assuming the plaintexts are
+/// English.
+///
+/// All ciphertexts are truncated to the shortest, and each column of bytes is
+/// broken as a single-byte xor. If `extend` is true, the keystream is then
+/// extended into the longer ciphertexts, one column at a time, for as long
+/// as at least [`MIN_COLUMN`] ciphertexts reach the column and some key byte
+/// makes it printable.
+///
+/// The more ciphertexts there are, the better this works; with only a few,
+/// some bytes will be wrong.
+pub fn attack<C: AsRef<[u8]>>(cts: &[C], extend: bool) -> Result<Recovered> {
+    if cts.len() < 2 {
+        return Err(Error::AttackFailed(format!(
+            "need at least two ciphertexts, not {}",
+            cts.len()
+        )));
+    }
+    let common_len = cts.iter().map(|ct| ct.as_ref().len()).min().unwrap();
+    let max_len = cts.iter().map(|ct| ct.as_ref().len()).max().unwrap();
+    if common_len == 0 {
+        return Err(Error::TooShort { len: 0, min: 1 });
+    }
+    let mut keystream = Vec::with_capacity(max_len);
+    for i in 0..common_len {
+        let column: Vec<u8> = cts.iter().map(|ct| ct.as_ref()[i]).collect();
+        let (_score, k) = guess_single_byte_key(&column).ok_or_else(|| {
+            Error::AttackFailed(format!("no key byte makes column {i} look like English"))
+        })?;
+        keystream.push(k);
+    }
+    if extend {
+        for i in common_len..max_len {
+            let column: Vec<u8> = cts
+                .iter()
+                .filter_map(|ct| ct.as_ref().get(i))
+                .copied()
+                .collect();
+            if column.len() < MIN_COLUMN {
+                break;
+            }
+            let Some((_score, k)) = guess_single_byte_key(&column) else {
+                break;
+            };
+            keystream.push(k);
+        }
+    }
+    let plaintexts = cts
+        .iter()
+        .map(|ct| {
+            ct.as_ref()
+                .iter()
+                .zip(&keystream)
+                .map(|(c, k)| c ^ k)
+                .collect()
+        })
+        .collect();
+    Ok(Recovered {
+        keystream,
+        common_len,
+        plaintexts,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aes::{self, CounterLayout, Key};
+
+    const LINES: &[&str] = &[
+        "The quick brown fox jumps over the lazy dog, again and again",
+        "Sphinx of black quartz, judge my vow before the court rises",
+        "A stitch in time saves nine, or so my grandmother always said",
+        "Every morning the baker opened his shop at a quarter past five",
+        "Nobody expected the river to flood so early in the spring",
+        "She kept a small notebook of words she had never heard before",
+        "When the train finally arrived, the platform was almost empty",
+        "Only three of the candles were still burning by midnight",
+        "He could not remember where he had left the key to the shed",
+        "Rain fell on the old tin roof for most of the afternoon",
+        "If you want to go fast, go alone; if you want to go far, go together",
+        "Several ducks waddled across the road while the cars waited",
+        "My neighbour plays the trumpet badly but with great enthusiasm",
+        "Under the bridge there lived a family of very noisy geese",
+        "Questions about the budget were left for the next meeting",
+        "Large waves crashed against the harbour wall all night long",
+        "You will find the recipe on the back of the flour packet",
+        "Children ran through the park chasing a bright red kite",
+        "Dinner was served late because the oven had broken again",
+        "Autumn leaves covered the path that led down to the lake",
+    ];
+
+    fn encrypt_all(lines: &[&str]) -> Vec<Vec<u8>> {
+        let key = Key::random();
+        lines
+            .iter()
+            .map(|l| {
+                aes::encrypt_aes_ctr(l.as_bytes(), &[0; 8], CounterLayout::LittleEndian64, &key)
+            })
+            .collect()
+    }
+
+    /// The fraction of bytes recovered correctly.
+    fn accuracy(recovered: &Recovered, lines: &[&str]) -> f64 {
+        let (mut right, mut total) = (0, 0);
+        for (p, l) in recovered.plaintexts.iter().zip(lines) {
+            right += p.iter().zip(l.as_bytes()).filter(|(a, b)| a == b).count();
+            total += p.len();
+        }
+        right as f64 / total as f64
+    }
+
+    #[test]
+    fn truncated() {
+        let recovered = attack(&encrypt_all(LINES), false).unwrap();
+        let shortest = LINES.iter().map(|l| l.len()).min().unwrap();
+        assert_eq!(recovered.common_len, shortest);
+        assert_eq!(recovered.keystream.len(), shortest);
+        assert!(recovered.plaintexts.iter().all(|p| p.len() == shortest));
+        assert!(accuracy(&recovered, LINES) > 0.9);
+    }
+
+    #[test]
+    fn extended() {
+        let recovered = attack(&encrypt_all(LINES), true).unwrap();
+        assert!(recovered.keystream.len() > recovered.common_len);
+        assert!(accuracy(&recovered, LINES) > 0.9);
+    }
+
+    #[test]
+    fn too_few() {
+        assert!(matches!(
+            attack(&[b"only one"], false),
+            Err(Error::AttackFailed(_))
+        ));
+        assert_eq!(
+            attack(&[&b"one"[..], b""], false),
+            Err(Error::TooShort { len: 0, min: 1 })
+        );
+    }
+}
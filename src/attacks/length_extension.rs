@@ -0,0 +1,135 @@
+//! Length extension against Merkle–Damgård hashes.
+//!
+//! A Merkle–Damgård hash's digest is just its internal state after the last
+//! block, so anyone who has `H(secret || message)` can carry on hashing from
+//! there, and get `H(secret || message || glue || extra)` for any `extra`,
+//! where `glue` is the padding the original message got. They need to know
+//! the length of the secret to make the glue, but can just try them all.
+//! This breaks "secret-prefix" MACs.
+//!
+//! This works for any [Resumable] hash. SHA-224 and SHA-384 aren't, because
+//! their digests are truncated, which leaves part of the state to guess.
+//!
+//! See <https://cryptopals.com/sets/4/challenges/29> and
+//! <https://cryptopals.com/sets/4/challenges/30>.
+
+use crate::hash::Resumable;
+use crate::{Error, Result};
+
+/// A message and MAC that will verify if the secret is the guessed length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forgery {
+    pub secret_len: usize,
+    /// The original message, the glue, and the appended data.
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+/// Given the `mac` of `secret || message`, forge a MAC for `message || glue ||
+/// append`, for each guess at the secret's length.
+pub fn forge<H: Resumable>(
+    mac: &[u8],
+    message: &[u8],
+    secret_lens: impl IntoIterator<Item = usize>,
+    append: &[u8],
+) -> Result<Vec<Forgery>> {
+    if mac.len() != H::DIGEST_SIZE {
+        return Err(Error::AttackFailed(format!(
+            "the MAC should be {} bytes, not {}",
+            H::DIGEST_SIZE,
+            mac.len()
+        )));
+    }
+    Ok(secret_lens
+        .into_iter()
+        .map(|secret_len| {
+            let glue = H::padding((secret_len + message.len()) as u64);
+            let glued_len = (secret_len + message.len() + glue.len()) as u64;
+            let mut h = H::from_digest(mac, glued_len);
+            h.update(append);
+            Forgery {
+                secret_len,
+                message: [message, &glue, append].concat(),
+                mac: h.finalize(),
+            }
+        })
+        .collect())
+}
+
+/// Forge a MAC for `message || glue || append`, trying each secret length
+/// against `verify` until one is accepted.
+pub fn forge_verified<H, V>(
+    mac: &[u8],
+    message: &[u8],
+    secret_lens: impl IntoIterator<Item = usize>,
+    append: &[u8],
+    verify: &V,
+) -> Result<Forgery>
+where
+    H: Resumable,
+    V: Fn(&[u8], &[u8]) -> bool + ?Sized,
+{
+    forge::<H>(mac, message, secret_lens, append)?
+        .into_iter()
+        .find(|f| verify(&f.message, &f.mac))
+        .ok_or_else(|| Error::AttackFailed("no guessed secret length was accepted".into()))
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::md4::Md4;
+    use crate::md5::Md5;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    fn secret_prefix_mac<H: Resumable>(key: &[u8], message: &[u8]) -> Vec<u8> {
+        H::digest(&[key, message].concat())
+    }
+
+    fn check_forgery<H: Resumable>(key: &[u8], message: &[u8], append: &[u8]) {
+        let mac = secret_prefix_mac::<H>(key, message);
+        let verify = |m: &[u8], t: &[u8]| secret_prefix_mac::<H>(key, m) == t;
+        let forgery = forge_verified::<H, _>(&mac, message, 0..100, append, &verify).unwrap();
+        assert_eq!(forgery.secret_len, key.len());
+        assert!(forgery.message.starts_with(message));
+        assert!(forgery.message.ends_with(append));
+    }
+
+    proptest! {
+        #[test]
+        fn forged_mac_verifies(key in prop::collection::vec(any::<u8>(), 0..100),
+                               message: Vec<u8>, append: Vec<u8>) {
+            check_forgery::<Sha1>(&key, &message, &append);
+            check_forgery::<Md4>(&key, &message, &append);
+            check_forgery::<Md5>(&key, &message, &append);
+            check_forgery::<Sha256>(&key, &message, &append);
+            check_forgery::<Sha512>(&key, &message, &append);
+        }
+    }
+
+    #[test]
+    fn one_forgery_per_guess() {
+        let mac = secret_prefix_mac::<Md5>(b"key", b"msg");
+        let forgeries = forge::<Md5>(&mac, b"msg", 0..=10, b"more").unwrap();
+        assert_eq!(forgeries.len(), 11);
+        assert_eq!(forgeries[3].message.len(), 64 - 3 + 4);
+        let mac = secret_prefix_mac::<Sha512>(b"key", b"msg");
+        let forgeries = forge::<Sha512>(&mac, b"msg", 0..=10, b"more").unwrap();
+        assert_eq!(forgeries[3].message.len(), 128 - 3 + 4);
+    }
+
+    #[test]
+    fn wrong_guesses_fail() {
+        let mac = secret_prefix_mac::<Sha1>(b"a long secret key", b"msg");
+        let verify = |m: &[u8], t: &[u8]| secret_prefix_mac::<Sha1>(b"a long secret key", m) == t;
+        assert!(matches!(
+            forge_verified::<Sha1, _>(&mac, b"msg", 0..10, b"x", &verify),
+            Err(Error::AttackFailed(_))
+        ));
+        assert!(forge::<Md4>(&mac, b"msg", 0..10, b"x").is_err());
+    }
+}
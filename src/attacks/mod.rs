@@ -0,0 +1,47 @@
+//! Attacks against the constructions in this crate, written against black-box
+//! oracles so that they can be pointed at other implementations too.
+
+pub mod bitflip;
+pub mod crib_drag;
+pub mod cut_and_paste;
+pub mod ecb_byte_at_a_time;
+pub mod fingerprint;
+pub mod fixed_nonce_ctr;
+pub mod length_extension;
+pub mod mt19937;
+pub mod padding_oracle;
+
+/// Something that encrypts attacker-chosen input, perhaps along with other
+/// text the attacker doesn't know.
+///
+/// This is implemented for any `Fn(input) -> Vec<u8>`.
+pub trait Oracle {
+    /// Return the ciphertext for `input`.
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F> Oracle for F
+where
+    F: Fn(&[u8]) -> Vec<u8>,
+{
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+/// An oracle that counts how often it's asked.
+pub(crate) struct Counted<'a, O: ?Sized> {
+    oracle: &'a O,
+    pub queries: usize,
+}
+
+impl<'a, O: Oracle + ?Sized> Counted<'a, O> {
+    pub fn new(oracle: &'a O) -> Self {
+        Counted { oracle, queries: 0 }
+    }
+
+    pub fn ask(&mut self, input: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        self.oracle.encrypt(input)
+    }
+}
@@ -0,0 +1,101 @@
+//! Attacks on the MT19937 Mersenne Twister.
+//!
+//! The tempering that turns each word of state into an output is
+//! invertible, so 624 consecutive outputs give away the whole state, and
+//! with it every later output. And a generator seeded with something
+//! guessable, such as the time or a 16-bit key, can be found by trying every
+//! seed.
+//!
+//! See <https://cryptopals.com/sets/3/challenges/22>,
+//! <https://cryptopals.com/sets/3/challenges/23> and
+//! <https://cryptopals.com/sets/3/challenges/24>.
+
+use rand::RngCore;
+
+use crate::random::{mt_stream_xor, Clock, Mt19937, MT_N};
+use crate::{Error, Result};
+
+/// Undo `y ^= y >> shift`.
+fn undo_shift_right(y: u32, shift: u32) -> u32 {
+    // Each round recovers another `shift` bits, from the top down.
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ (x >> shift);
+    }
+    x
+}
+
+/// Undo `y ^= (y << shift) & mask`.
+fn undo_shift_left(y: u32, shift: u32, mask: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ ((x << shift) & mask);
+    }
+    x
+}
+
+/// Recover the word of state that was tempered into `y`.
+pub fn untemper(y: u32) -> u32 {
+    let y = undo_shift_right(y, 18);
+    let y = undo_shift_left(y, 15, 0xefc6_0000);
+    let y = undo_shift_left(y, 7, 0x9d2c_5680);
+    undo_shift_right(y, 11)
+}
+
+/// Clone a generator from the first 624 outputs after a twist, such as the
+/// first 624 from a freshly seeded generator.
+///
+/// The clone's next output is the one after those 624.
+pub fn clone_mt(outputs: &[u32]) -> Result<Mt19937> {
+    let outputs = outputs.get(..MT_N).ok_or(Error::TooShort {
+        len: outputs.len(),
+        min: MT_N,
+    })?;
+    let mut state = [0u32; MT_N];
+    for (s, &y) in state.iter_mut().zip(outputs) {
+        *s = untemper(y);
+    }
+    Ok(Mt19937::from_state(state))
+}
+
+/// Find the timestamp a generator was seeded with, given its first output,
+/// trying every second from `window` seconds ago until now.
+pub fn recover_time_seed(first_output: u32, clock: &dyn Clock, window: u64) -> Option<u32> {
+    let now = clock.now();
+    (now.saturating_sub(window)..=now)
+        .rev()
+        .map(|t| t as u32)
+        .find(|&seed| Mt19937::new(seed).next_u32() == first_output)
+}
+
+/// Find the 16-bit key of a [`mt_stream_xor`] ciphertext whose plaintext is
+/// known to end with `known_suffix`.
+pub fn recover_mt_stream_seed(ct: &[u8], known_suffix: &[u8]) -> Option<u16> {
+    if known_suffix.is_empty() || known_suffix.len() > ct.len() {
+        return None;
+    }
+    (0..=u16::MAX).find(|&seed| mt_stream_xor(ct, seed).ends_with(known_suffix))
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::random::mt_temper;
+
+    proptest! {
+        #[test]
+        fn untemper_inverts_temper(x: u32) {
+            assert_eq!(untemper(mt_temper(x)), x);
+        }
+    }
+
+    #[test]
+    fn clone_needs_enough_outputs() {
+        assert_eq!(
+            clone_mt(&[0; 100]).err(),
+            Some(Error::TooShort { len: 100, min: 624 })
+        );
+    }
+}
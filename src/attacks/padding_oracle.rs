@@ -0,0 +1,223 @@
+//! The CBC padding oracle attack.
+//!
+//! If a server reveals whether a CBC ciphertext decrypts to validly padded
+//! plaintext, an attacker can recover the plaintext one byte at a time, by
+//! sending each ciphertext block with a modified previous block (or IV) and
+//! adjusting the modification until the padding is accepted.
+//!
+//! See <https://cryptopals.com/sets/3/challenges/17>.
+
+use crate::{pkcs7, Error, Result};
+
+/// Something that says whether a CBC ciphertext is validly PKCS#7 padded.
+///
+/// This is implemented for any `Fn(ct, iv) -> bool`.
+pub trait PaddingOracle {
+    /// True if `ct` decrypts with `iv` to validly padded plaintext.
+    fn is_padded(&self, ct: &[u8], iv: &[u8]) -> bool;
+}
+
+impl<F> PaddingOracle for F
+where
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    fn is_padded(&self, ct: &[u8], iv: &[u8]) -> bool {
+        self(ct, iv)
+    }
+}
+
+/// The result of a padding oracle attack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// The recovered plaintext, still including its padding.
+    pub padded: Vec<u8>,
+    /// The total number of oracle queries.
+    pub queries: usize,
+    /// The number of oracle queries spent on each block.
+    pub block_queries: Vec<usize>,
+}
+
+impl Recovered {
+    /// The plaintext with the padding removed, if it's valid.
+    pub fn plaintext(&self) -> Result<&[u8]> {
+        pkcs7::try_unpad(&self.padded)
+    }
+}
+
+/// Recover the plaintext of `ct` by asking the oracle about modified copies.
+///
+/// The block size is taken from the length of the IV. Every block is
+/// recovered, the first by modifying the IV.
+pub fn attack<O: PaddingOracle + ?Sized>(ct: &[u8], iv: &[u8], oracle: &O) -> Result<Recovered> {
+    let bs = iv.len();
+    if bs == 0 || !ct.len().is_multiple_of(bs) {
+        return Err(Error::NotBlockAligned {
+            len: ct.len(),
+            block_size: bs,
+        });
+    }
+    let mut padded = Vec::with_capacity(ct.len());
+    let mut block_queries = Vec::new();
+    let mut prev = iv;
+    for block in ct.chunks_exact(bs) {
+        let mut queries = 0;
+        let intermediate = recover_intermediate(block, oracle, &mut queries)?;
+        padded.extend(intermediate.iter().zip(prev).map(|(a, b)| a ^ b));
+        block_queries.push(queries);
+        prev = block;
+    }
+    Ok(Recovered {
+        padded,
+        queries: block_queries.iter().sum(),
+        block_queries,
+    })
+}
+
+/// Find the block cipher decryption of one ciphertext block, before it is
+/// xor'd with the previous block.
+fn recover_intermediate<O: PaddingOracle + ?Sized>(
+    block: &[u8],
+    oracle: &O,
+    queries: &mut usize,
+) -> Result<Vec<u8>> {
+    let bs = block.len();
+    let mut intermediate = vec![0u8; bs];
+    let mut iv = vec![0u8; bs];
+    let mut ask = |iv: &[u8]| {
+        *queries += 1;
+        oracle.is_padded(block, iv)
+    };
+    for pad in 1..=bs {
+        let i = bs - pad;
+        // Make the bytes already found decrypt to the padding value.
+        for j in (i + 1)..bs {
+            iv[j] = intermediate[j] ^ pad as u8;
+        }
+        let mut found = None;
+        for b in 0..=255u8 {
+            iv[i] = b;
+            if !ask(&iv) {
+                continue;
+            }
+            if pad == 1 && i > 0 {
+                // The padding might have been accepted because the
+                // plaintext ends in, say, 02 02 rather than 01. Changing the
+                // second-last byte rules that out, since it makes no
+                // difference to a single 01.
+                let mut check = iv.clone();
+                check[i - 1] ^= 0xff;
+                if !ask(&check) {
+                    continue;
+                }
+            }
+            found = Some(b);
+            break;
+        }
+        match found {
+            Some(b) => intermediate[i] = b ^ pad as u8,
+            None => {
+                return Err(Error::AttackFailed(format!(
+                    "no IV gives valid padding {pad} at byte {i}"
+                )))
+            }
+        }
+    }
+    Ok(intermediate)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::aes::{self, random_iv, random_iv_from, Key};
+    use crate::block::{BlockCipher, BlockMode, Cbc};
+    use crate::toy::ToyCipher;
+
+    /// A padding oracle for CBC under `cipher`.
+    fn oracle(cipher: &dyn BlockCipher) -> impl Fn(&[u8], &[u8]) -> bool + '_ {
+        move |ct, iv| {
+            Cbc::new(iv)
+                .try_decrypt(cipher, ct)
+                .is_ok_and(|plain| pkcs7::try_unpad(&plain).is_ok())
+        }
+    }
+
+    /// A "cipher" that leaves blocks unchanged, so that we can choose
+    /// exactly what the attack sees.
+    struct Identity;
+
+    impl BlockCipher for Identity {
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn encrypt_block(&self, _block: &mut [u8]) {}
+
+        fn decrypt_block(&self, _block: &mut [u8]) {}
+    }
+
+    /// When the second-last byte already decrypts to 02, the first accepted
+    /// guess for the last byte makes 02 02 rather than 01; the attack must
+    /// not be fooled into taking it.
+    #[test]
+    fn ambiguous_last_byte() {
+        let ct = [0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x02, 0x02];
+        let iv = [0x10; 8];
+        let recovered = attack(&ct, &iv, &oracle(&Identity)).unwrap();
+        assert_eq!(
+            recovered.padded,
+            [0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x12, 0x12]
+        );
+    }
+
+    #[test]
+    fn counts_queries() {
+        let key = Key::random();
+        let iv = random_iv();
+        let ct = aes::encrypt_aes_cbc(&pkcs7::pad(b"hello", 16), &iv, &key);
+        let recovered = attack(&ct, &iv, &oracle(&key)).unwrap();
+        assert_eq!(recovered.block_queries.len(), 1);
+        assert_eq!(recovered.queries, recovered.block_queries[0]);
+        // At least one query per byte, and at most 256 plus one check.
+        assert!(recovered.queries >= 16);
+        assert!(recovered.queries <= 16 * 256 + 256);
+    }
+
+    #[test]
+    fn misaligned_ciphertext_is_an_error() {
+        let key = Key::random();
+        assert!(attack(&[0; 20], &[0; 16], &oracle(&key)).is_err());
+    }
+
+    /// An oracle that never accepts the padding gives the attack nothing to
+    /// work with.
+    #[test]
+    fn rejecting_oracle_fails() {
+        let never = |_: &[u8], _: &[u8]| false;
+        let err = attack(&[0; 16], &[0; 16], &never).unwrap_err();
+        assert!(matches!(err, Error::AttackFailed(_)));
+    }
+
+    proptest! {
+        #[test]
+        fn recover_any_text_aes(seed: u64, plain: Vec<u8>) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let key = Key::random_from(&mut rng);
+            let iv = random_iv_from(&mut rng);
+            let ct = aes::encrypt_aes_cbc(&pkcs7::pad(&plain, 16), &iv, &key);
+            let recovered = attack(&ct, &iv, &oracle(&key)).unwrap();
+            assert_eq!(recovered.plaintext().unwrap(), plain);
+        }
+
+        #[test]
+        fn recover_any_text_toy(key: u64, iv: [u8; 8], plain: Vec<u8>) {
+            let toy = ToyCipher::new(key);
+            let ct = Cbc::new(&iv).encrypt(&toy, &pkcs7::pad(&plain, 8));
+            let recovered = attack(&ct, &iv, &oracle(&toy)).unwrap();
+            assert_eq!(recovered.plaintext().unwrap(), plain);
+        }
+    }
+}
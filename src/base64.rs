@@ -83,9 +83,9 @@ pub fn base64_file_to_bytes(path: &str) -> Vec<u8> {
 
 fn to_base64_char(b: u8) -> char {
     match b {
-        0..=25 => (b'A' + b),
-        26..=51 => (b'a' + b - 26),
-        52..=61 => (b'0' + b - 52),
+        0..=25 => b'A' + b,
+        26..=51 => b'a' + b - 26,
+        52..=61 => b'0' + b - 52,
         62 => b'+',
         63 => b'/',
         _ => panic!("{} is out of range", b),
@@ -95,9 +95,9 @@ fn to_base64_char(b: u8) -> char {
 
 fn from_base64_char(c: u8) -> u32 {
     let r = match c {
-        b'A'..=b'Z' => (c - b'A'),
-        b'a'..=b'z' => (c - b'a' + 26),
-        b'0'..=b'9' => (c - b'0' + 52),
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
         b'+' => 62,
         b'/' => 63,
         _ => panic!("{c:?} is not a base64 character"),
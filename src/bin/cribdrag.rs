@@ -0,0 +1,118 @@
+//! Interactively crib-drag messages encrypted with the same keystream.
+//!
+//! Reads base64 ciphertexts from a file, one per line, then takes commands
+//! on stdin:
+//!
+//! ```text
+//! drag I CRIB        try CRIB at every offset in message I
+//! lock I OFFSET TEXT confirm message I holds TEXT at OFFSET
+//! unlock OFFSET LEN  forget some keystream
+//! show               print what's known of every message
+//! key                print the keystream, as hex
+//! quit
+//! ```
+//!
+//! The crib or text is the rest of the line, so it can have spaces.
+
+use std::fs::read_to_string;
+use std::io::{stdin, stdout, BufRead, Write};
+
+use cryptopals::attacks::crib_drag::CribDrag;
+use cryptopals::base64_to_bytes;
+use cryptopals::strs::bytes_to_lossy_ascii;
+
+/// How many candidates to show for each drag.
+const SHOW_CANDIDATES: usize = 10;
+
+pub fn main() {
+    let mut argv = std::env::args();
+    if argv.len() != 2 {
+        eprintln!("usage: cribdrag CIPHERTEXTS");
+        std::process::exit(1);
+    }
+    let path = argv.nth(1).unwrap();
+    let cts = read_to_string(path)
+        .unwrap()
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(base64_to_bytes)
+        .collect();
+    let mut session = CribDrag::new(cts);
+    show(&session);
+    let mut lines = stdin().lock().lines();
+    loop {
+        print!("> ");
+        stdout().flush().unwrap();
+        let Some(line) = lines.next() else { break };
+        let line = line.unwrap();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((&line, ""));
+        let result = match cmd {
+            "drag" => drag(&session, rest),
+            "lock" => lock(&mut session, rest),
+            "unlock" => unlock(&mut session, rest),
+            "show" => {
+                show(&session);
+                Ok(())
+            }
+            "key" => {
+                println!("{}", session.show_keystream());
+                Ok(())
+            }
+            "quit" => break,
+            "" => Ok(()),
+            _ => Err(format!("unknown command {cmd:?}")),
+        };
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+        }
+    }
+}
+
+fn show(session: &CribDrag) {
+    for i in 0..session.ciphertexts().len() {
+        println!("{i:3} {}", session.show(i).unwrap());
+    }
+}
+
+fn drag(session: &CribDrag, args: &str) -> Result<(), String> {
+    let (index, crib) = args.split_once(' ').ok_or("usage: drag I CRIB")?;
+    let index = index.parse().map_err(|e| format!("bad index: {e}"))?;
+    let found = session
+        .drag(index, crib.as_bytes())
+        .map_err(|e| e.to_string())?;
+    if found.is_empty() {
+        println!("no offset gives printable text");
+    }
+    for cand in found.iter().take(SHOW_CANDIDATES) {
+        println!("offset {} score {}", cand.offset, cand.score);
+        for (j, text) in cand.revealed.iter().enumerate() {
+            if let Some(text) = text {
+                println!("  {j:3} {:?}", bytes_to_lossy_ascii(text));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lock(session: &mut CribDrag, args: &str) -> Result<(), String> {
+    let usage = "usage: lock I OFFSET TEXT";
+    let (index, rest) = args.split_once(' ').ok_or(usage)?;
+    let (offset, text) = rest.split_once(' ').ok_or(usage)?;
+    let index = index.parse().map_err(|e| format!("bad index: {e}"))?;
+    let offset = offset.parse().map_err(|e| format!("bad offset: {e}"))?;
+    session
+        .lock(index, offset, text.as_bytes())
+        .map_err(|e| e.to_string())?;
+    show(session);
+    Ok(())
+}
+
+fn unlock(session: &mut CribDrag, args: &str) -> Result<(), String> {
+    let usage = "usage: unlock OFFSET LEN";
+    let (offset, len) = args.split_once(' ').ok_or(usage)?;
+    let offset = offset.parse().map_err(|e| format!("bad offset: {e}"))?;
+    let len = len.trim().parse().map_err(|e| format!("bad length: {e}"))?;
+    session.unlock(offset, len);
+    show(session);
+    Ok(())
+}
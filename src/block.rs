@@ -0,0 +1,884 @@
+//! Block ciphers and modes of operation, independent of any particular cipher.
+//!
+//! [BlockCipher] covers AES ([crate::aes::Key]), our own
+//! [crate::rijndael::Rijndael], and the 8-byte [crate::toy::ToyCipher], so
+//! that modes and attacks can be written once and run against any of them.
+
+use crate::{Error, Result};
+
+/// A keyed block cipher.
+pub trait BlockCipher {
+    /// The size of one block in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Encrypt one block in place.
+    ///
+    /// Panics if `block` is not exactly one block long.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    /// Decrypt one block in place.
+    ///
+    /// Panics if `block` is not exactly one block long.
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+/// A mode of operation, using a block cipher to encrypt messages of many blocks.
+///
+/// Modes that work on whole blocks, like ECB and CBC, don't add padding, and
+/// fail if the text is not a multiple of the block size.
+pub trait BlockMode {
+    /// Encrypt, or return an error if the IV or text is the wrong length.
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decrypt, or return an error if the IV or text is the wrong length.
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>>;
+
+    /// Encrypt, panicking if the IV or text is the wrong length.
+    #[must_use]
+    fn encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Vec<u8> {
+        self.try_encrypt(cipher, plain)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Decrypt, panicking if the IV or text is the wrong length.
+    #[must_use]
+    fn decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Vec<u8> {
+        self.try_decrypt(cipher, ct)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+fn xor_into(dst: &mut [u8], src: &[u8]) {
+    debug_assert_eq!(dst.len(), src.len());
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+fn check_block_padded(text: &[u8], block_size: usize) -> Result<()> {
+    if text.len().is_multiple_of(block_size) {
+        Ok(())
+    } else {
+        Err(Error::NotBlockAligned {
+            len: text.len(),
+            block_size,
+        })
+    }
+}
+
+fn check_iv(iv: &[u8], block_size: usize) -> Result<()> {
+    if iv.len() == block_size {
+        Ok(())
+    } else {
+        Err(Error::BadIvLength {
+            expected: block_size,
+            actual: iv.len(),
+        })
+    }
+}
+
+/// The modes of operation that a [Chain] can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chaining {
+    Ecb,
+    Cbc,
+    Pcbc,
+    /// CBC, with the last two blocks left to [CbcCts].
+    CbcCts(CiphertextStealing),
+    Ofb,
+    /// CFB with a full-block segment.
+    Cfb,
+    /// CFB with an 8-bit segment.
+    Cfb8,
+}
+
+impl Chaining {
+    /// True for the modes that work on whole blocks, and so need padding.
+    pub fn is_padded(self) -> bool {
+        matches!(self, Chaining::Ecb | Chaining::Cbc | Chaining::Pcbc)
+    }
+
+    /// True for the modes that work a byte at a time, with no padding.
+    pub fn is_stream(self) -> bool {
+        matches!(self, Chaining::Ofb | Chaining::Cfb | Chaining::Cfb8)
+    }
+}
+
+/// The state a mode of operation carries from one block to the next, so
+/// that text can be processed in pieces.
+///
+/// The block modes take a whole block at a time through
+/// [Chain::encrypt_block] and [Chain::decrypt_block], and the feedback
+/// modes any number of bytes through [Chain::apply_stream].
+#[derive(Debug, Clone)]
+pub struct Chain {
+    chaining: Chaining,
+    /// The previous ciphertext block for CBC, the previous plaintext xor the
+    /// previous ciphertext block for PCBC, the keystream block for OFB, and
+    /// the shift register for CFB. Initially the IV.
+    reg: Vec<u8>,
+    /// For OFB and CFB, how much of the keystream block in `reg` is used,
+    /// where 0 means it's time to encrypt `reg` for the next one.
+    used: usize,
+    /// Space for a block that's needed while `reg` is updated.
+    scratch: Vec<u8>,
+}
+
+impl Chain {
+    /// Start a chain from an IV, which ECB ignores.
+    ///
+    /// Panics if the IV is not one block.
+    pub fn new(chaining: Chaining, iv: &[u8], block_size: usize) -> Chain {
+        Chain::try_new(chaining, iv, block_size).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [Chain::new], but returns an error for a bad IV.
+    pub fn try_new(chaining: Chaining, iv: &[u8], block_size: usize) -> Result<Chain> {
+        let iv = if chaining == Chaining::Ecb {
+            vec![0; block_size]
+        } else {
+            check_iv(iv, block_size)?;
+            iv.to_owned()
+        };
+        Ok(Chain {
+            chaining,
+            reg: iv,
+            used: 0,
+            scratch: vec![0; block_size],
+        })
+    }
+
+    pub fn chaining(&self) -> Chaining {
+        self.chaining
+    }
+
+    /// The previous ciphertext block, for CBC.
+    pub(crate) fn prev_ct(&self) -> &[u8] {
+        &self.reg
+    }
+
+    /// Encrypt one block in place.
+    ///
+    /// Panics for the feedback modes, or if `block` is not one block long.
+    pub fn encrypt_block<C: BlockCipher + ?Sized>(&mut self, cipher: &C, block: &mut [u8]) {
+        match self.chaining {
+            Chaining::Ecb => cipher.encrypt_block(block),
+            Chaining::Cbc | Chaining::CbcCts(_) => {
+                xor_into(block, &self.reg);
+                cipher.encrypt_block(block);
+                self.reg.copy_from_slice(block);
+            }
+            Chaining::Pcbc => {
+                xor_into(block, &self.reg);
+                // The block is now the plaintext xor the old chain, so this
+                // leaves the plaintext.
+                xor_into(&mut self.reg, block);
+                cipher.encrypt_block(block);
+                xor_into(&mut self.reg, block);
+            }
+            _ => panic!("{:?} is not a block mode", self.chaining),
+        }
+    }
+
+    /// Decrypt one block in place.
+    ///
+    /// Panics for the feedback modes, or if `block` is not one block long.
+    pub fn decrypt_block<C: BlockCipher + ?Sized>(&mut self, cipher: &C, block: &mut [u8]) {
+        match self.chaining {
+            Chaining::Ecb => cipher.decrypt_block(block),
+            Chaining::Cbc | Chaining::CbcCts(_) => {
+                self.scratch.copy_from_slice(block);
+                cipher.decrypt_block(block);
+                xor_into(block, &self.reg);
+                std::mem::swap(&mut self.reg, &mut self.scratch);
+            }
+            Chaining::Pcbc => {
+                self.scratch.copy_from_slice(block);
+                cipher.decrypt_block(block);
+                xor_into(block, &self.reg);
+                xor_into(&mut self.scratch, block);
+                std::mem::swap(&mut self.reg, &mut self.scratch);
+            }
+            _ => panic!("{:?} is not a block mode", self.chaining),
+        }
+    }
+
+    /// Encrypt or decrypt any number of bytes in place, in one of the
+    /// feedback modes.
+    ///
+    /// Panics for the block modes.
+    pub fn apply_stream<C: BlockCipher + ?Sized>(
+        &mut self,
+        cipher: &C,
+        data: &mut [u8],
+        decrypting: bool,
+    ) {
+        let bs = self.reg.len();
+        for x in data {
+            let input = *x;
+            match self.chaining {
+                Chaining::Ofb => {
+                    if self.used == 0 {
+                        cipher.encrypt_block(&mut self.reg);
+                    }
+                    *x ^= self.reg[self.used];
+                    self.used = (self.used + 1) % bs;
+                }
+                // Used keystream bytes are overwritten with ciphertext, so
+                // that once it's all used the register holds the block to
+                // encrypt for the next keystream.
+                Chaining::Cfb => {
+                    if self.used == 0 {
+                        cipher.encrypt_block(&mut self.reg);
+                    }
+                    *x ^= self.reg[self.used];
+                    self.reg[self.used] = if decrypting { input } else { *x };
+                    self.used = (self.used + 1) % bs;
+                }
+                Chaining::Cfb8 => {
+                    self.scratch.copy_from_slice(&self.reg);
+                    cipher.encrypt_block(&mut self.scratch);
+                    *x ^= self.scratch[0];
+                    self.reg.rotate_left(1);
+                    self.reg[bs - 1] = if decrypting { input } else { *x };
+                }
+                _ => panic!("{:?} is not a feedback mode", self.chaining),
+            }
+        }
+    }
+}
+
+/// Encrypt or decrypt a whole text with a new chain: a block at a time for
+/// the block modes, which need whole blocks, or all at once for the feedback
+/// modes.
+fn run_chain(
+    chaining: Chaining,
+    iv: &[u8],
+    cipher: &dyn BlockCipher,
+    text: &[u8],
+    decrypting: bool,
+) -> Result<Vec<u8>> {
+    let bs = cipher.block_size();
+    let mut chain = Chain::try_new(chaining, iv, bs)?;
+    let mut out = text.to_owned();
+    if chaining.is_stream() {
+        chain.apply_stream(cipher, &mut out, decrypting);
+        return Ok(out);
+    }
+    check_block_padded(text, bs)?;
+    for block in out.chunks_mut(bs) {
+        if decrypting {
+            chain.decrypt_block(cipher, block);
+        } else {
+            chain.encrypt_block(cipher, block);
+        }
+    }
+    Ok(out)
+}
+
+/// Electronic codebook mode: each block encrypted independently.
+pub struct Ecb;
+
+impl BlockMode for Ecb {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Ecb, &[], cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Ecb, &[], cipher, ct, true)
+    }
+}
+
+/// Cipher block chaining mode: each plaintext block is xor'd with the previous
+/// ciphertext block (or the IV) before encryption.
+pub struct Cbc {
+    iv: Vec<u8>,
+}
+
+impl Cbc {
+    pub fn new(iv: &[u8]) -> Cbc {
+        Cbc { iv: iv.to_owned() }
+    }
+}
+
+impl BlockMode for Cbc {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cbc, &self.iv, cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cbc, &self.iv, cipher, ct, true)
+    }
+}
+
+/// Propagating cipher block chaining: each plaintext block is xor'd with both
+/// the previous plaintext and the previous ciphertext block before encryption.
+///
+/// A corrupted ciphertext block garbles every following block, unlike CBC.
+pub struct Pcbc {
+    iv: Vec<u8>,
+}
+
+impl Pcbc {
+    pub fn new(iv: &[u8]) -> Pcbc {
+        Pcbc { iv: iv.to_owned() }
+    }
+}
+
+impl BlockMode for Pcbc {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Pcbc, &self.iv, cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Pcbc, &self.iv, cipher, ct, true)
+    }
+}
+
+/// Where the stolen partial block goes in CBC ciphertext stealing, following
+/// the addendum to NIST SP 800-38A.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiphertextStealing {
+    /// The truncated penultimate block comes before the final block.
+    Cs1,
+    /// Like CS1, but the last two blocks are swapped when the final block is
+    /// partial, so whole-block text is the same as plain CBC.
+    Cs2,
+    /// The last two blocks are always swapped, as in Kerberos.
+    Cs3,
+}
+
+/// CBC with ciphertext stealing: text of any length of at least one block is
+/// encrypted without padding, and the ciphertext is the same length.
+///
+/// The final partial block is zero-filled and encrypted as usual, and then the
+/// penultimate ciphertext block is truncated to the same length, since its
+/// tail can be recovered while decrypting the final block.
+pub struct CbcCts {
+    iv: Vec<u8>,
+    variant: CiphertextStealing,
+}
+
+impl CbcCts {
+    pub fn new(iv: &[u8], variant: CiphertextStealing) -> CbcCts {
+        CbcCts {
+            iv: iv.to_owned(),
+            variant,
+        }
+    }
+
+    /// True if the last two blocks are swapped, when the final block has
+    /// `tail_len` bytes.
+    fn swapped(&self, tail_len: usize, block_size: usize) -> bool {
+        match self.variant {
+            CiphertextStealing::Cs1 => false,
+            CiphertextStealing::Cs2 => tail_len < block_size,
+            CiphertextStealing::Cs3 => true,
+        }
+    }
+}
+
+/// Split a text of at least one block into the number of blocks, and the
+/// length of the final, possibly partial, block.
+fn cts_shape(len: usize, block_size: usize) -> Result<(usize, usize)> {
+    if len < block_size {
+        return Err(Error::TooShort {
+            len,
+            min: block_size,
+        });
+    }
+    let n = len.div_ceil(block_size);
+    Ok((n, len - (n - 1) * block_size))
+}
+
+impl BlockMode for CbcCts {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        let bs = cipher.block_size();
+        let (n, d) = cts_shape(plain.len(), bs)?;
+        let mut padded = plain.to_owned();
+        padded.resize(n * bs, 0);
+        let mut ct = Cbc::new(&self.iv).try_encrypt(cipher, &padded)?;
+        if n == 1 {
+            return Ok(ct);
+        }
+        let last = ct.split_off((n - 1) * bs);
+        let penultimate = ct.split_off((n - 2) * bs);
+        if self.swapped(d, bs) {
+            ct.extend_from_slice(&last);
+            ct.extend_from_slice(&penultimate[..d]);
+        } else {
+            ct.extend_from_slice(&penultimate[..d]);
+            ct.extend_from_slice(&last);
+        }
+        Ok(ct)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        let bs = cipher.block_size();
+        let (n, d) = cts_shape(ct.len(), bs)?;
+        if n == 1 {
+            return Cbc::new(&self.iv).try_decrypt(cipher, ct);
+        }
+        let (head, tail) = ct.split_at((n - 2) * bs);
+        let (stolen, last) = if self.swapped(d, bs) {
+            let (last, stolen) = tail.split_at(bs);
+            (stolen, last)
+        } else {
+            tail.split_at(d)
+        };
+        // Decrypting the last block gives the zero-filled final plaintext xor
+        // the whole penultimate ciphertext block, whose tail was dropped.
+        let mut z = last.to_owned();
+        cipher.decrypt_block(&mut z);
+        let mut full = head.to_owned();
+        full.extend_from_slice(stolen);
+        full.extend_from_slice(&z[d..]);
+        let mut final_plain = z[..d].to_owned();
+        xor_into(&mut final_plain, stolen);
+        let mut plain = Cbc::new(&self.iv).try_decrypt(cipher, &full)?;
+        plain.extend_from_slice(&final_plain);
+        Ok(plain)
+    }
+}
+
+/// Output feedback mode: the keystream is the IV encrypted repeatedly.
+///
+/// Encryption and decryption are the same, and the text can be any length.
+pub struct Ofb {
+    iv: Vec<u8>,
+}
+
+impl Ofb {
+    pub fn new(iv: &[u8]) -> Ofb {
+        Ofb { iv: iv.to_owned() }
+    }
+}
+
+impl BlockMode for Ofb {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Ofb, &self.iv, cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Ofb, &self.iv, cipher, ct, true)
+    }
+}
+
+/// Cipher feedback mode with a full-block segment (CFB-128 for AES): each
+/// ciphertext block is encrypted to make the keystream for the next.
+///
+/// The text can be any length; a final partial block uses part of the keystream.
+pub struct Cfb {
+    iv: Vec<u8>,
+}
+
+impl Cfb {
+    pub fn new(iv: &[u8]) -> Cfb {
+        Cfb { iv: iv.to_owned() }
+    }
+}
+
+impl BlockMode for Cfb {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cfb, &self.iv, cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cfb, &self.iv, cipher, ct, true)
+    }
+}
+
+/// Cipher feedback mode with an 8-bit segment: one block encryption per byte,
+/// shifting each ciphertext byte into the register.
+pub struct Cfb8 {
+    iv: Vec<u8>,
+}
+
+impl Cfb8 {
+    pub fn new(iv: &[u8]) -> Cfb8 {
+        Cfb8 { iv: iv.to_owned() }
+    }
+}
+
+impl BlockMode for Cfb8 {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cfb8, &self.iv, cipher, plain, false)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        run_chain(Chaining::Cfb8, &self.iv, cipher, ct, true)
+    }
+}
+
+/// How the nonce and the block counter are arranged within each counter block.
+///
+/// The counter takes the last bytes of the block, and the nonce fills the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterLayout {
+    /// A 64-bit little-endian block counter, after a 64-bit nonce in a 16-byte
+    /// block.
+    ///
+    /// This is the layout used by <https://cryptopals.com/sets/3/challenges/18>.
+    LittleEndian64,
+    /// A 32-bit big-endian block counter, after a 96-bit nonce in a 16-byte
+    /// block, as in NIST SP 800-38A and GCM.
+    BigEndian32,
+}
+
+impl CounterLayout {
+    /// The number of bytes of the counter block taken by the counter.
+    pub fn counter_len(self) -> usize {
+        match self {
+            CounterLayout::LittleEndian64 => 8,
+            CounterLayout::BigEndian32 => 4,
+        }
+    }
+
+    /// The number of bytes of nonce that fill the rest of a counter block.
+    pub fn nonce_len(self, block_size: usize) -> usize {
+        block_size - self.counter_len()
+    }
+}
+
+/// A seekable counter-mode keystream.
+///
+/// The stream keeps a byte position, so that text can be encrypted or
+/// decrypted in pieces, or starting from any offset.
+pub struct Ctr<'c, C: BlockCipher + ?Sized> {
+    cipher: &'c C,
+    layout: CounterLayout,
+    nonce: Vec<u8>,
+    initial_counter: u64,
+    pos: u64,
+}
+
+impl<'c, C: BlockCipher + ?Sized> Ctr<'c, C> {
+    /// Make a new keystream positioned at the start.
+    ///
+    /// Panics if the nonce is the wrong length for the layout and block size.
+    pub fn new(cipher: &'c C, layout: CounterLayout, nonce: &[u8]) -> Ctr<'c, C> {
+        Ctr::try_new(cipher, layout, nonce).unwrap_or_else(|e| panic!("{e} for {layout:?}"))
+    }
+
+    /// Make a new keystream positioned at the start, or return an error if
+    /// the nonce is the wrong length for the layout and block size.
+    pub fn try_new(cipher: &'c C, layout: CounterLayout, nonce: &[u8]) -> Result<Ctr<'c, C>> {
+        if nonce.len() != layout.nonce_len(cipher.block_size()) {
+            return Err(Error::BadNonceLength(nonce.len()));
+        }
+        Ok(Ctr {
+            cipher,
+            layout,
+            nonce: nonce.to_owned(),
+            initial_counter: 0,
+            pos: 0,
+        })
+    }
+
+    /// Start the block counter at `counter` rather than at 0.
+    ///
+    /// The counter wraps around within its field in the counter block.
+    #[must_use]
+    pub fn with_initial_counter(self, counter: u64) -> Ctr<'c, C> {
+        Ctr {
+            initial_counter: counter,
+            ..self
+        }
+    }
+
+    /// Move to byte position `pos` in the keystream.
+    pub fn seek(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// The current byte position in the keystream.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Return the counter block for block number `block_index`.
+    fn counter_block(&self, block_index: u64) -> Vec<u8> {
+        let counter = self.initial_counter.wrapping_add(block_index);
+        let mut block = self.nonce.clone();
+        match self.layout {
+            CounterLayout::LittleEndian64 => block.extend_from_slice(&counter.to_le_bytes()),
+            CounterLayout::BigEndian32 => block.extend_from_slice(&(counter as u32).to_be_bytes()),
+        }
+        block
+    }
+
+    /// Return the keystream for block number `block_index`.
+    pub fn keystream_block(&self, block_index: u64) -> Vec<u8> {
+        let mut block = self.counter_block(block_index);
+        self.cipher.encrypt_block(&mut block);
+        block
+    }
+
+    /// Xor the keystream into `buf`, starting from the current position, and
+    /// advance the position past it.
+    pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+        let bs = self.cipher.block_size() as u64;
+        let mut done = 0;
+        while done < buf.len() {
+            let block_index = self.pos / bs;
+            let skip = (self.pos % bs) as usize;
+            let n = std::cmp::min(bs as usize - skip, buf.len() - done);
+            let ks = self.keystream_block(block_index);
+            xor_into(&mut buf[done..(done + n)], &ks[skip..(skip + n)]);
+            done += n;
+            self.pos = self.pos.wrapping_add(n as u64);
+        }
+    }
+}
+
+/// Counter mode, as a [BlockMode] with the counter starting from 0.
+///
+/// Encryption and decryption are the same, and the text can be any length.
+pub struct CtrMode {
+    layout: CounterLayout,
+    nonce: Vec<u8>,
+}
+
+impl CtrMode {
+    pub fn new(layout: CounterLayout, nonce: &[u8]) -> CtrMode {
+        CtrMode {
+            layout,
+            nonce: nonce.to_owned(),
+        }
+    }
+}
+
+impl BlockMode for CtrMode {
+    fn try_encrypt(&self, cipher: &dyn BlockCipher, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut buf = plain.to_owned();
+        Ctr::try_new(cipher, self.layout, &self.nonce)?.apply_keystream(&mut buf);
+        Ok(buf)
+    }
+
+    fn try_decrypt(&self, cipher: &dyn BlockCipher, ct: &[u8]) -> Result<Vec<u8>> {
+        self.try_encrypt(cipher, ct)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::aes::{self, Key};
+    use crate::pkcs7;
+    use crate::rijndael::Rijndael;
+    use crate::toy::ToyCipher;
+
+    #[test]
+    fn little_endian_counter_block() {
+        let key = Key::from_slice(b"YELLOW SUBMARINE");
+        let ctr = Ctr::new(&key, CounterLayout::LittleEndian64, &[0xaa; 8]);
+        assert_eq!(
+            ctr.counter_block(0x0102),
+            [0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 2, 1, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn big_endian_counter_wraps() {
+        let key = Key::from_slice(b"YELLOW SUBMARINE");
+        let ctr = Ctr::new(&key, CounterLayout::BigEndian32, &[0xaa; 12])
+            .with_initial_counter(0xffff_ffff);
+        assert_eq!(ctr.counter_block(0)[12..], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(ctr.counter_block(1)[12..], [0, 0, 0, 0]);
+        assert_eq!(ctr.counter_block(1)[..12], [0xaa; 12]);
+    }
+
+    /// The position wraps around the end of the keystream, as the counter
+    /// does.
+    #[test]
+    fn seek_near_end_of_keystream() {
+        let key = Key::from_slice(b"YELLOW SUBMARINE");
+        let mut ctr = Ctr::new(&key, CounterLayout::LittleEndian64, &[0; 8]);
+        ctr.seek(u64::MAX - 3);
+        let mut buf = [0u8; 8];
+        ctr.apply_keystream(&mut buf);
+        assert_eq!(ctr.position(), 4);
+        assert_eq!(buf[..4], ctr.keystream_block(u64::MAX / 16)[12..]);
+        assert_eq!(buf[4..], ctr.keystream_block(0)[..4]);
+    }
+
+    #[test]
+    fn counter_layouts_on_small_blocks() {
+        let toy = ToyCipher::new(0x0123_4567_89ab_cdef);
+        assert_eq!(CounterLayout::LittleEndian64.nonce_len(toy.block_size()), 0);
+        assert_eq!(CounterLayout::BigEndian32.nonce_len(toy.block_size()), 4);
+        let ctr = Ctr::new(&toy, CounterLayout::BigEndian32, &[9; 4]).with_initial_counter(5);
+        assert_eq!(ctr.counter_block(1), [9, 9, 9, 9, 0, 0, 0, 6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_nonce_length() {
+        let key = Key::from_slice(b"YELLOW SUBMARINE");
+        let _ = Ctr::new(&key, CounterLayout::BigEndian32, &[0; 8]);
+    }
+
+    /// A flipped bit in PCBC ciphertext garbles all the following plaintext,
+    /// whereas in CBC it only affects two blocks.
+    #[test]
+    fn pcbc_propagates_errors() {
+        let toy = ToyCipher::new(99);
+        let iv = [1u8; 8];
+        let plain = [b'a'; 64];
+        for (mode, expected_damaged) in [
+            (Box::new(Cbc::new(&iv)) as Box<dyn BlockMode>, 2),
+            (Box::new(Pcbc::new(&iv)), 7),
+        ] {
+            let mut ct = mode.encrypt(&toy, &plain);
+            ct[9] ^= 1;
+            let ret = mode.decrypt(&toy, &ct);
+            let damaged = ret
+                .chunks(8)
+                .zip(plain.chunks(8))
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(damaged, expected_damaged);
+        }
+    }
+
+    /// The variants differ only in the order of the last two blocks, and CS2
+    /// is plain CBC on whole blocks.
+    #[test]
+    fn ciphertext_stealing_variants() {
+        let toy = ToyCipher::new(11);
+        let iv = [3u8; 8];
+        let plain = b"twenty-one bytes long";
+        let cs = |v| CbcCts::new(&iv, v).encrypt(&toy, plain);
+        let (cs1, cs2, cs3) = (
+            cs(CiphertextStealing::Cs1),
+            cs(CiphertextStealing::Cs2),
+            cs(CiphertextStealing::Cs3),
+        );
+        assert_eq!(cs1.len(), plain.len());
+        assert_eq!(cs2, cs3);
+        assert_eq!(cs1[..8], cs2[..8]);
+        assert_eq!(cs1[8..13], cs2[16..]);
+        assert_eq!(cs1[13..], cs2[8..16]);
+
+        let whole = b"sixteen bytes!!!";
+        let cbc = Cbc::new(&iv).encrypt(&toy, whole);
+        assert_eq!(
+            CbcCts::new(&iv, CiphertextStealing::Cs1).encrypt(&toy, whole),
+            cbc
+        );
+        assert_eq!(
+            CbcCts::new(&iv, CiphertextStealing::Cs2).encrypt(&toy, whole),
+            cbc
+        );
+        let cs3 = CbcCts::new(&iv, CiphertextStealing::Cs3).encrypt(&toy, whole);
+        assert_eq!(cs3[..8], cbc[8..]);
+        assert_eq!(cs3[8..], cbc[..8]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ciphertext_stealing_needs_one_block() {
+        let toy = ToyCipher::new(11);
+        let _ = CbcCts::new(&[0; 8], CiphertextStealing::Cs1).encrypt(&toy, b"short");
+    }
+
+    /// Stream-like modes don't need padding, and decrypt partial blocks.
+    #[test]
+    fn feedback_modes_take_any_length() {
+        let toy = ToyCipher::new(7);
+        let iv = [3u8; 8];
+        let plain = b"not a multiple of 8";
+        let modes: [Box<dyn BlockMode>; 3] = [
+            Box::new(Ofb::new(&iv)),
+            Box::new(Cfb::new(&iv)),
+            Box::new(Cfb8::new(&iv)),
+        ];
+        for mode in modes {
+            let ct = mode.encrypt(&toy, plain);
+            assert_eq!(ct.len(), plain.len());
+            assert_eq!(mode.decrypt(&toy, &ct), plain);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn ecb_needs_whole_blocks() {
+        let _ = Ecb.encrypt(&ToyCipher::new(1), &[0; 12]);
+    }
+
+    /// Every mode, set up for a cipher with the given block size.
+    fn all_modes(block_size: usize) -> Vec<Box<dyn BlockMode>> {
+        vec![
+            Box::new(Ecb),
+            Box::new(Cbc::new(&vec![0x42; block_size])),
+            Box::new(CtrMode::new(
+                CounterLayout::BigEndian32,
+                &vec![7; CounterLayout::BigEndian32.nonce_len(block_size)],
+            )),
+            Box::new(Pcbc::new(&vec![0x42; block_size])),
+            Box::new(Ofb::new(&vec![0x42; block_size])),
+            Box::new(Cfb::new(&vec![0x42; block_size])),
+            Box::new(Cfb8::new(&vec![0x42; block_size])),
+            Box::new(CbcCts::new(
+                &vec![0x42; block_size],
+                CiphertextStealing::Cs1,
+            )),
+            Box::new(CbcCts::new(
+                &vec![0x42; block_size],
+                CiphertextStealing::Cs3,
+            )),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_ciphertext_stealing(key: u64, plain in prop::collection::vec(any::<u8>(), 8..100)) {
+            let toy = ToyCipher::new(key);
+            for variant in [CiphertextStealing::Cs1, CiphertextStealing::Cs2, CiphertextStealing::Cs3] {
+                let mode = CbcCts::new(&[9; 8], variant);
+                let ct = mode.encrypt(&toy, &plain);
+                assert_eq!(ct.len(), plain.len());
+                assert_eq!(mode.decrypt(&toy, &ct), plain);
+            }
+        }
+
+        #[test]
+        fn roundtrip_all_modes_toy_cipher(key: u64, plain: Vec<u8>) {
+            let toy = ToyCipher::new(key);
+            let padded = pkcs7::pad(&plain, toy.block_size());
+            for mode in all_modes(toy.block_size()) {
+                let ct = mode.encrypt(&toy, &padded);
+                assert_eq!(ct.len(), padded.len());
+                assert_eq!(mode.decrypt(&toy, &ct), padded);
+            }
+        }
+
+        /// AES through the trait gives the same answers as the `aes` functions,
+        /// and our own Rijndael gives the same answers as both.
+        #[test]
+        fn aes_through_trait(key: [u8; 16], plain: Vec<u8>) {
+            let aes_key = Key::from_slice(&key);
+            let rijndael = Rijndael::new(&key).unwrap();
+            let iv = [0x42; 16];
+            let padded = pkcs7::pad(&plain, 16);
+            let nonce = [7; 12];
+            let expected = [
+                aes::encrypt_aes_ecb(&padded, &aes_key),
+                aes::encrypt_aes_cbc(&padded, &iv, &aes_key),
+                aes::encrypt_aes_ctr(&padded, &nonce, CounterLayout::BigEndian32, &aes_key),
+                aes::encrypt_aes_pcbc(&padded, &iv, &aes_key),
+                aes::encrypt_aes_ofb(&padded, &iv, &aes_key),
+                aes::encrypt_aes_cfb(&padded, &iv, &aes_key),
+                aes::encrypt_aes_cfb8(&padded, &iv, &aes_key),
+                aes::encrypt_aes_cbc_cts(&padded, &iv, CiphertextStealing::Cs1, &aes_key),
+                aes::encrypt_aes_cbc_cts(&padded, &iv, CiphertextStealing::Cs3, &aes_key),
+            ];
+            for (mode, expected) in all_modes(16).iter().zip(expected) {
+                assert_eq!(mode.encrypt(&aes_key, &padded), expected);
+                assert_eq!(mode.encrypt(&rijndael, &padded), expected);
+            }
+        }
+    }
+}
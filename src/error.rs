@@ -0,0 +1,60 @@
+//! Errors from encryption, decryption, padding, and attacks.
+//!
+//! These are returned for bad input that might come from an untrusted peer,
+//! so that a server can reject it rather than panicking.
+
+use std::fmt;
+
+/// Something wrong with a key, IV, or text passed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The key is not a length the cipher accepts.
+    BadKeyLength(usize),
+    /// The IV is not the expected length, usually one block.
+    BadIvLength { expected: usize, actual: usize },
+    /// The nonce is not a length the mode accepts.
+    BadNonceLength(usize),
+    /// The authentication tag length is not one the mode allows.
+    BadTagLength(usize),
+    /// The text is not a whole number of blocks.
+    NotBlockAligned { len: usize, block_size: usize },
+    /// The text is shorter than the mode needs.
+    TooShort { len: usize, min: usize },
+    /// The block size can't be used for PKCS#7 padding.
+    BadBlockSize(usize),
+    /// The PKCS#7 padding on decrypted text is not valid.
+    BadPadding,
+    /// The authentication tag does not match.
+    TagMismatch,
+    /// An attack couldn't proceed, typically because the oracle didn't
+    /// behave as the attack expects.
+    AttackFailed(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BadKeyLength(l) => write!(f, "bad key length {l}"),
+            Error::BadIvLength { expected, actual } => {
+                write!(f, "IV should be {expected} bytes, not {actual}")
+            }
+            Error::BadNonceLength(l) => write!(f, "bad nonce length {l}"),
+            Error::BadTagLength(l) => write!(f, "bad tag length {l}"),
+            Error::NotBlockAligned { len, block_size } => write!(
+                f,
+                "text of {len} bytes is not a whole number of {block_size}-byte blocks"
+            ),
+            Error::TooShort { len, min } => {
+                write!(f, "text of {len} bytes is shorter than {min} bytes")
+            }
+            Error::BadBlockSize(bs) => write!(f, "can't pad to a block size of {bs}"),
+            Error::BadPadding => write!(f, "bad padding"),
+            Error::TagMismatch => write!(f, "authentication tag does not match"),
+            Error::AttackFailed(why) => write!(f, "attack failed: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
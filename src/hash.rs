@@ -0,0 +1,104 @@
+//! A common interface to the hand-written hash functions, so that
+//! constructions like [HMAC](crate::hmac) and attacks like
+//! [length extension](crate::attacks::length_extension) can be written once.
+
+use crate::merkle_damgard::{Core, MerkleDamgard, Untruncated};
+
+/// A streaming hash function.
+pub trait Hash: Clone + Default {
+    /// Size of the blocks the compression function takes, in bytes.
+    const BLOCK_SIZE: usize;
+    /// Size of a digest in bytes.
+    const DIGEST_SIZE: usize;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    /// Hash `data` in one go.
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut h = Self::default();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+/// A hash whose digest is its whole state, so that hashing can carry on
+/// from a digest, as length extension does.
+pub trait Resumable: Hash {
+    /// The padding appended to a message of `len` bytes before the last
+    /// compression.
+    fn padding(len: u64) -> Vec<u8>;
+
+    /// Carry on hashing from `digest`, which is the hash of a message that
+    /// was `len` bytes once padded.
+    ///
+    /// Panics if `digest` is the wrong size, or `len` is not a whole number
+    /// of blocks.
+    fn from_digest(digest: &[u8], len: u64) -> Self;
+}
+
+impl<C: Core> Hash for MerkleDamgard<C> {
+    const BLOCK_SIZE: usize = C::BLOCK_SIZE;
+    const DIGEST_SIZE: usize = C::DIGEST_SIZE;
+
+    fn update(&mut self, data: &[u8]) {
+        MerkleDamgard::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MerkleDamgard::finalize(self).as_ref().to_vec()
+    }
+}
+
+impl<C: Untruncated> Resumable for MerkleDamgard<C> {
+    fn padding(len: u64) -> Vec<u8> {
+        MerkleDamgard::<C>::padding(len.into())
+    }
+
+    fn from_digest(digest: &[u8], len: u64) -> Self {
+        MerkleDamgard::from_state(MerkleDamgard::<C>::state_from_digest(digest), len.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bytes_to_hex;
+    use crate::md4::Md4;
+    use crate::md5::Md5;
+    use crate::sha1::Sha1;
+    use crate::sha256::{Sha224, Sha256};
+    use crate::sha512::{Sha384, Sha512};
+
+    fn abc<H: Hash>() -> String {
+        let d = H::digest(b"abc");
+        assert_eq!(d.len(), H::DIGEST_SIZE);
+        bytes_to_hex(&d)
+    }
+
+    #[test]
+    fn trait_matches_inherent() {
+        assert_eq!(abc::<Sha1>(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(abc::<Md4>(), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(abc::<Md5>(), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            abc::<Sha224>(),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+        assert_eq!(
+            abc::<Sha256>(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            abc::<Sha384>(),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163\
+            1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+        );
+        assert_eq!(
+            abc::<Sha512>(),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+            2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+}
@@ -13,7 +13,7 @@ fn hex_digit_to_u16(c: char) -> u8 {
 }
 
 pub fn hex_to_bytes(s: &str) -> Bytes {
-    assert!(s.len() % 2 == 0);
+    assert!(s.len().is_multiple_of(2));
     let mut b = BytesMut::new();
     let mut ch = s.chars();
     while let Some(c1) = ch.next() {
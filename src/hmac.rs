@@ -0,0 +1,311 @@
+//! HMAC, from RFC 2104, over any [Hash].
+//!
+//! Unlike a secret-prefix MAC, HMAC hashes the message under an inner key and
+//! then hashes that digest again under an outer key, so length extension
+//! doesn't work on it.
+//!
+//! Also here are two ways to compare MACs: one that takes the same time
+//! whatever the inputs, and one that stops at the first difference, and so
+//! leaks how many leading bytes were right.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::hash::Hash;
+use crate::{Error, Result};
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// A HMAC in progress.
+#[derive(Debug, Clone)]
+pub struct Hmac<H: Hash> {
+    inner: H,
+    /// The key, padded to a block, xor'd with [OPAD].
+    outer_key: Vec<u8>,
+}
+
+impl<H: Hash> Hmac<H> {
+    pub fn new(key: &[u8]) -> Hmac<H> {
+        // Keys longer than a block are hashed first; shorter ones are padded
+        // with zeros.
+        let mut key = if key.len() > H::BLOCK_SIZE {
+            H::digest(key)
+        } else {
+            key.to_owned()
+        };
+        key.resize(H::BLOCK_SIZE, 0);
+        let mut inner = H::default();
+        inner.update(&key.iter().map(|k| k ^ IPAD).collect::<Vec<u8>>());
+        Hmac {
+            inner,
+            outer_key: key.iter().map(|k| k ^ OPAD).collect(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = H::default();
+        outer.update(&self.outer_key);
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+
+    /// Check that `tag` is the MAC of everything so far, in constant time.
+    pub fn verify(self, tag: &[u8]) -> Result<()> {
+        if constant_time_eq(&self.finalize(), tag) {
+            Ok(())
+        } else {
+            Err(Error::TagMismatch)
+        }
+    }
+}
+
+/// Calculate the HMAC of `message` in one go.
+pub fn hmac<H: Hash>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut h = Hmac::<H>::new(key);
+    h.update(message);
+    h.finalize()
+}
+
+/// Compare two byte strings, taking the same time whatever their contents.
+///
+/// The lengths aren't secret: strings of different lengths are unequal
+/// straight away.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    // Keep the compiler from turning the fold into an early exit.
+    std::hint::black_box(diff) == 0
+}
+
+/// Compare two byte strings one byte at a time, sleeping for `delay` after
+/// each byte that matches, and stopping at the first that doesn't.
+///
+/// Don't use this: it's deliberately slow and leaky, to make timing attacks
+/// easy to demonstrate.
+pub fn insecure_eq(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        sleep(delay);
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::md5::Md5;
+    use crate::sha1::Sha1;
+    use crate::sha256::{Sha224, Sha256};
+    use crate::sha512::{Sha384, Sha512};
+    use crate::{bytes_to_hex, hex_to_bytes};
+
+    /// Keys and data from RFC 2202, shared by the MD5 and SHA-1 cases except
+    /// for the key lengths.
+    fn rfc2202_cases(key_len: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        vec![
+            (vec![0x0b; key_len], b"Hi There".to_vec()),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec()),
+            (vec![0xaa; key_len], vec![0xdd; 50]),
+            (
+                hex_to_bytes("0102030405060708090a0b0c0d0e0f10111213141516171819").to_vec(),
+                vec![0xcd; 50],
+            ),
+            (vec![0x0c; key_len], b"Test With Truncation".to_vec()),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data"
+                    .to_vec(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn rfc2202_hmac_sha1() {
+        let expected = [
+            "b617318655057264e28bc0b6fb378c8ef146be00",
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            "125d7342b9ac11cd91a39af48aa17b4f63f175d3",
+            "4c9007f4026250c6bc8414f9bf50c86c2d7235da",
+            "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04",
+            "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+        ];
+        for ((key, data), expected) in rfc2202_cases(20).iter().zip(expected) {
+            assert_eq!(bytes_to_hex(&hmac::<Sha1>(key, data)), expected);
+        }
+    }
+
+    #[test]
+    fn rfc2202_hmac_md5() {
+        let expected = [
+            "9294727a3638bb1c13f48ef8158bfc9d",
+            "750c783e6ab0b503eaa86e310a5db738",
+            "56be34521d144c88dbb8c733f0e8b3f6",
+            "697eaf0aca3a3aea3a75164746ffaa79",
+            "56461ef2342edc00f9bab995690efd4c",
+            "6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd",
+            "6f630fad67cda0ee1fb1f562db3aa53e",
+        ];
+        for ((key, data), expected) in rfc2202_cases(16).iter().zip(expected) {
+            assert_eq!(bytes_to_hex(&hmac::<Md5>(key, data)), expected);
+        }
+    }
+
+    /// Check `H` against the keys and data from RFC 4231. Case 5's expected
+    /// MAC is truncated to 128 bits, so only a prefix is compared.
+    fn check_rfc4231<H: Hash>(expected: [&str; 7]) {
+        let cases = [
+            (vec![0x0b; 20], b"Hi There".to_vec()),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec()),
+            (vec![0xaa; 20], vec![0xdd; 50]),
+            (
+                hex_to_bytes("0102030405060708090a0b0c0d0e0f10111213141516171819").to_vec(),
+                vec![0xcd; 50],
+            ),
+            (vec![0x0c; 20], b"Test With Truncation".to_vec()),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+            ),
+            (
+                vec![0xaa; 131],
+                b"This is a test using a larger than block-size key and a larger than \
+                block-size data. The key needs to be hashed before being used by the HMAC \
+                algorithm."
+                    .to_vec(),
+            ),
+        ];
+        for ((key, data), expected) in cases.iter().zip(expected) {
+            let mac = bytes_to_hex(&hmac::<H>(key, data));
+            assert!(mac.starts_with(expected), "{mac} != {expected}");
+        }
+    }
+
+    #[test]
+    fn rfc4231_hmac_sha224() {
+        check_rfc4231::<Sha224>([
+            "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+            "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+            "7fb3cb3588c6c1f6ffa9694d7d6ad2649365b0c1f65d69d1ec8333ea",
+            "6c11506874013cac6a2abc1bb382627cec6a90d86efc012de7afec5a",
+            "0e2aea68a90c8d37c988bcdb9fca6fa8",
+            "95e9a0db962095adaebe9b2d6f0dbce2d499f112f2d2b7273fa6870e",
+            "3a854166ac5d9f023f54d517d0b39dbd946770db9c2b95c9f6f565d1",
+        ]);
+    }
+
+    #[test]
+    fn rfc4231_hmac_sha256() {
+        check_rfc4231::<Sha256>([
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            "a3b6167473100ee06e0c796c2955552b",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+        ]);
+    }
+
+    #[test]
+    fn rfc4231_hmac_sha384() {
+        check_rfc4231::<Sha384>([
+            "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59c\
+            faea9ea9076ede7f4af152e8b2fa9cb6",
+            "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e\
+            8e2240ca5e69e2c78b3239ecfab21649",
+            "88062608d3e6ad8a0aa2ace014c8a86f0aa635d947ac9febe83ef4e55966144b\
+            2a5ab39dc13814b94e3ab6e101a34f27",
+            "3e8a69b7783c25851933ab6290af6ca77a9981480850009cc5577c6e1f573b4e\
+            6801dd23c4a7d679ccf8a386c674cffb",
+            "3abf34c3503b2a23a46efc619baef897",
+            "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c6\
+            0c2ef6ab4030fe8296248df163f44952",
+            "6617178e941f020d351e2f254e8fd32c602420feb0b8fb9adccebb82461e99c5\
+            a678cc31e799176d3860e6110c46523e",
+        ]);
+    }
+
+    #[test]
+    fn rfc4231_hmac_sha512() {
+        check_rfc4231::<Sha512>([
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+            daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+            9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39\
+            bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb",
+            "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3db\
+            a91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd",
+            "415fad6271580a531d4179bc891d87a6",
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+            6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+            "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944\
+            b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58",
+        ]);
+    }
+
+    #[test]
+    fn verify() {
+        let tag = hmac::<Sha1>(b"key", b"message");
+        let mut h = Hmac::<Sha1>::new(b"key");
+        h.update(b"mess");
+        h.update(b"age");
+        assert_eq!(h.clone().verify(&tag), Ok(()));
+        assert_eq!(h.clone().verify(&tag[..19]), Err(Error::TagMismatch));
+        let mut bad = tag.clone();
+        bad[7] ^= 1;
+        assert_eq!(h.verify(&bad), Err(Error::TagMismatch));
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(key: Vec<u8>, data: Vec<u8>, split: usize) {
+            let (a, b) = data.split_at(split % (data.len() + 1));
+            let mut h = Hmac::<Sha256>::new(&key);
+            h.update(a);
+            h.update(b);
+            assert_eq!(h.finalize(), hmac::<Sha256>(&key, &data));
+        }
+
+        #[test]
+        fn comparisons_agree(a: Vec<u8>, b: Vec<u8>) {
+            assert_eq!(constant_time_eq(&a, &b), a == b);
+            assert_eq!(insecure_eq(&a, &b, Duration::ZERO), a == b);
+            assert!(constant_time_eq(&a, &a));
+        }
+    }
+
+    #[test]
+    fn insecure_eq_leaks_the_prefix() {
+        let delay = Duration::from_millis(5);
+        let start = Instant::now();
+        assert!(!insecure_eq(b"abcdef", b"xbcdef", delay));
+        let wrong_first = start.elapsed();
+        let start = Instant::now();
+        assert!(!insecure_eq(b"abcdef", b"abcdex", delay));
+        let wrong_last = start.elapsed();
+        assert!(wrong_last >= delay * 5);
+        assert!(wrong_first < wrong_last);
+    }
+}
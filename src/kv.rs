@@ -0,0 +1,62 @@
+//! `k=v&k=v` strings, as used for the user profiles in
+//! <https://cryptopals.com/sets/2/challenges/13>.
+
+use std::collections::BTreeMap;
+
+/// Parse `k=v` pairs separated by `&`, or None if any pair has no `=`.
+pub fn parse_kv(s: &str) -> Option<BTreeMap<String, String>> {
+    let mut map = BTreeMap::new();
+    for kvstr in s.split('&') {
+        let (kstr, vstr) = kvstr.split_once('=')?;
+        map.insert(kstr.into(), vstr.into());
+    }
+    Some(map)
+}
+
+/// Remove the metacharacters from a key or value.
+fn clean(s: &str) -> String {
+    s.replace(['&', '='], "")
+}
+
+/// Serialize a map, dropping any metacharacters in the keys and values.
+pub fn serialize_kv(kv: &BTreeMap<String, String>) -> String {
+    kv.iter()
+        .map(|(k, v)| format!("{}={}", clean(k), clean(v)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Make the encoded profile for a user with this email address.
+pub fn profile_for(email: &str) -> String {
+    format!("email={}&uid=10&role=user", clean(email))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kv_parse_example() {
+        let kv = parse_kv("foo=bar&baz=qux&zap=zazzle").expect("parse");
+        assert_eq!(kv.len(), 3);
+        assert_eq!(kv.get("foo").unwrap(), "bar");
+        assert_eq!(kv.get("baz").unwrap(), "qux");
+        assert_eq!(kv.get("zap").unwrap(), "zazzle");
+        assert_eq!(serialize_kv(&kv), "baz=qux&foo=bar&zap=zazzle");
+    }
+
+    #[test]
+    fn strings_are_cleaned() {
+        let mut kv = BTreeMap::new();
+        kv.insert("email".into(), "foo@bar.com&role=admin".into());
+        assert_eq!(serialize_kv(&kv), "email=foo@bar.comroleadmin");
+    }
+
+    #[test]
+    fn profile_example() {
+        assert_eq!(
+            profile_for("foo@bar.com"),
+            "email=foo@bar.com&uid=10&role=user"
+        );
+    }
+}
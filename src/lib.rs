@@ -1,16 +1,31 @@
 //! My cryptopals solutions.
 
 pub mod aes;
+pub mod attacks;
 pub mod base64;
+pub mod block;
 pub mod detect;
+pub mod error;
 pub mod freqs;
 pub mod hamming;
+pub mod hash;
 pub mod hex;
+pub mod hmac;
+pub mod kv;
+pub mod md4;
+pub mod md5;
+pub mod merkle_damgard;
 pub mod pkcs7;
 pub mod random;
+pub mod rijndael;
+pub mod sha1;
+pub mod sha256;
+pub mod sha512;
 pub mod strs;
+pub mod toy;
 pub mod xor;
 
 pub use base64::{base64_to_bytes, bytes_to_base64};
 pub use detect::score_english;
+pub use error::{Error, Result};
 pub use hex::{bytes_to_hex, hex_to_bytes};
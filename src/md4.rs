@@ -0,0 +1,160 @@
+//! MD4, written out by hand from RFC 1320.
+//!
+//! Like [crate::sha1], the internal state is exposed so that a hash can be
+//! resumed from a digest. MD4 is thoroughly broken and is here to be
+//! attacked.
+
+use crate::merkle_damgard::{Core, Endian, MerkleDamgard, Untruncated};
+
+/// Size of the blocks the compression function takes, in bytes.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Size of a digest in bytes.
+pub const DIGEST_SIZE: usize = 16;
+
+/// The state before any input, from RFC 1320 section 3.3.
+pub const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// The MD4 compression function, with little-endian 32-bit words.
+#[derive(Debug, Clone)]
+pub struct Md4Core;
+
+impl Core for Md4Core {
+    type Word = u32;
+    type State = [u32; 4];
+    type Len = u64;
+    type Digest = [u8; DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Little;
+    const INITIAL_STATE: [u32; 4] = INITIAL_STATE;
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+impl Untruncated for Md4Core {}
+
+/// A MD4 hash in progress.
+pub type Md4 = MerkleDamgard<Md4Core>;
+
+/// Hash `data` in one go.
+pub fn md4(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Md4::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// A round's function, additive constant, order of input words, and
+/// rotations.
+type Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+
+/// Mix one block into the state, as in RFC 1320 section 3.4.
+fn compress(state: &mut [u32; 4], block: &[u8; BLOCK_SIZE]) {
+    let mut x = [0u32; 16];
+    for (x, b) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *x = u32::from_le_bytes(b.try_into().unwrap());
+    }
+    let mut v = *state;
+    // Each round is 16 steps, each updating one of a, d, c, b in turn, from
+    // the other three.
+    let rounds: [Round; 3] = [
+        (
+            |x, y, z| (x & y) | (!x & z),
+            0,
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            [3, 7, 11, 19],
+        ),
+        (
+            |x, y, z| (x & y) | (x & z) | (y & z),
+            0x5a827999,
+            [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+            [3, 5, 9, 13],
+        ),
+        (
+            |x, y, z| x ^ y ^ z,
+            0x6ed9eba1,
+            [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+            [3, 9, 11, 15],
+        ),
+    ];
+    for (f, k, order, shifts) in rounds {
+        for (j, &i) in order.iter().enumerate() {
+            let t = (4 - j % 4) % 4;
+            let g = f(v[(t + 1) % 4], v[(t + 2) % 4], v[(t + 3) % 4]);
+            v[t] = v[t]
+                .wrapping_add(g)
+                .wrapping_add(x[i])
+                .wrapping_add(k)
+                .rotate_left(shifts[j % 4]);
+        }
+    }
+    for (s, v) in state.iter_mut().zip(v) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::bytes_to_hex;
+
+    /// The test suite from RFC 1320 appendix A.5.
+    #[test]
+    fn rfc1320_suite() {
+        for (input, expected) in [
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&md4(input.as_bytes())), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn padding_is_little_endian() {
+        assert_eq!(Md4::padding(0).len(), 64);
+        assert_eq!(Md4::padding(55).len(), 9);
+        assert_eq!(Md4::padding(56).len(), 72);
+        assert_eq!(&Md4::padding(3)[53..], &[24, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(data: Vec<u8>, split: usize) {
+            let (a, b) = data.split_at(split % (data.len() + 1));
+            let mut h = Md4::new();
+            h.update(a);
+            h.update(b);
+            assert_eq!(h.finalize(), md4(&data));
+        }
+
+        #[test]
+        fn resume_from_digest(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Md4::padding(msg.len() as u64);
+            let mut h = Md4::from_state(
+                Md4::state_from_digest(&md4(&msg)),
+                (msg.len() + pad.len()) as u64,
+            );
+            h.update(&more);
+            assert_eq!(h.finalize(), md4(&[&msg[..], &pad, &more].concat()));
+        }
+    }
+}
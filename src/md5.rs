@@ -0,0 +1,161 @@
+//! MD5, written out by hand from RFC 1321.
+//!
+//! Like [crate::sha1], the internal state is exposed so that a hash can be
+//! resumed from a digest. MD5 is broken for collisions and is here to be
+//! attacked.
+
+use lazy_static::lazy_static;
+
+use crate::merkle_damgard::{Core, Endian, MerkleDamgard, Untruncated};
+
+/// Size of the blocks the compression function takes, in bytes.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Size of a digest in bytes.
+pub const DIGEST_SIZE: usize = 16;
+
+/// The state before any input, from RFC 1321 section 3.3.
+pub const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// How far each step rotates, for each round.
+const SHIFTS: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+lazy_static! {
+    /// The additive constants: the integer part of 2^32 times abs(sin(i + 1)).
+    static ref T: [u32; 64] = {
+        let mut t = [0u32; 64];
+        for (i, t) in t.iter_mut().enumerate() {
+            *t = (((i + 1) as f64).sin().abs() * 4294967296.0) as u32;
+        }
+        t
+    };
+}
+
+/// The MD5 compression function, which has the same sizes and byte order
+/// as MD4.
+#[derive(Debug, Clone)]
+pub struct Md5Core;
+
+impl Core for Md5Core {
+    type Word = u32;
+    type State = [u32; 4];
+    type Len = u64;
+    type Digest = [u8; DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Little;
+    const INITIAL_STATE: [u32; 4] = INITIAL_STATE;
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+impl Untruncated for Md5Core {}
+
+/// A MD5 hash in progress.
+pub type Md5 = MerkleDamgard<Md5Core>;
+
+/// Hash `data` in one go.
+pub fn md5(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Md5::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Mix one block into the state, as in RFC 1321 section 3.4.
+fn compress(state: &mut [u32; 4], block: &[u8; BLOCK_SIZE]) {
+    let mut x = [0u32; 16];
+    for (x, b) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *x = u32::from_le_bytes(b.try_into().unwrap());
+    }
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, k) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((b & d) | (c & !d), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let rotated = a
+            .wrapping_add(f)
+            .wrapping_add(x[k])
+            .wrapping_add(T[i])
+            .rotate_left(SHIFTS[i / 16][i % 4]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(rotated);
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::bytes_to_hex;
+
+    /// The test suite from RFC 1321 appendix A.5.
+    #[test]
+    fn rfc1321_suite() {
+        for (input, expected) in [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&md5(input.as_bytes())), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn sine_table() {
+        // RFC 1321 section 3.4 lists the first and last.
+        assert_eq!(T[0], 0xd76aa478);
+        assert_eq!(T[63], 0xeb86d391);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(data: Vec<u8>, split: usize) {
+            let (a, b) = data.split_at(split % (data.len() + 1));
+            let mut h = Md5::new();
+            h.update(a);
+            h.update(b);
+            assert_eq!(h.finalize(), md5(&data));
+        }
+
+        #[test]
+        fn resume_from_digest(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Md5::padding(msg.len() as u64);
+            let mut h = Md5::from_state(
+                Md5::state_from_digest(&md5(&msg)),
+                (msg.len() + pad.len()) as u64,
+            );
+            h.update(&more);
+            assert_eq!(h.finalize(), md5(&[&msg[..], &pad, &more].concat()));
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! The Merkle–Damgård construction shared by MD4, MD5, SHA-1 and SHA-2.
+//!
+//! Each of these hashes buffers its input into blocks, feeds each block to a
+//! compression function along with the state so far, and pads the end of
+//! the message with a 1 bit, zeros, and the message length. They differ only
+//! in the compression function, the size and byte order of the words, and
+//! the size of the length: those are a [`Core`], and [`MerkleDamgard`] does
+//! the rest.
+
+use std::array::TryFromSliceError;
+use std::fmt::{Debug, Display};
+use std::marker::PhantomData;
+use std::mem::{size_of, size_of_val};
+
+/// The byte order of the words of the state, and of the length in the
+/// padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// A word of the state: `u32` or `u64`.
+pub trait Word: Copy + Debug + Eq {
+    /// Read a word from exactly `size_of::<Self>()` bytes.
+    fn from_bytes(bytes: &[u8], endian: Endian) -> Self;
+
+    /// Write the word into exactly `size_of::<Self>()` bytes.
+    fn write_bytes(self, out: &mut [u8], endian: Endian);
+}
+
+impl Word for u32 {
+    fn from_bytes(bytes: &[u8], endian: Endian) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        match endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        }
+    }
+
+    fn write_bytes(self, out: &mut [u8], endian: Endian) {
+        out.copy_from_slice(&match endian {
+            Endian::Big => self.to_be_bytes(),
+            Endian::Little => self.to_le_bytes(),
+        })
+    }
+}
+
+impl Word for u64 {
+    fn from_bytes(bytes: &[u8], endian: Endian) -> u64 {
+        let bytes = bytes.try_into().unwrap();
+        match endian {
+            Endian::Big => u64::from_be_bytes(bytes),
+            Endian::Little => u64::from_le_bytes(bytes),
+        }
+    }
+
+    fn write_bytes(self, out: &mut [u8], endian: Endian) {
+        out.copy_from_slice(&match endian {
+            Endian::Big => self.to_be_bytes(),
+            Endian::Little => self.to_le_bytes(),
+        })
+    }
+}
+
+/// The count of input bytes: `u64`, or `u128` for SHA-512. The padding
+/// holds it in bits, in the same number of bytes, wrapping if it's too big.
+pub trait Length: Copy + Debug + Display + Eq + From<u64> + Into<u128> {
+    fn wrapping_add_bytes(self, n: usize) -> Self;
+}
+
+impl Length for u64 {
+    fn wrapping_add_bytes(self, n: usize) -> u64 {
+        self.wrapping_add(n as u64)
+    }
+}
+
+impl Length for u128 {
+    fn wrapping_add_bytes(self, n: usize) -> u128 {
+        self.wrapping_add(n as u128)
+    }
+}
+
+/// The parts of a Merkle–Damgård hash that differ from one to the next.
+pub trait Core: Debug + Clone {
+    type Word: Word;
+    /// The state, which is an array of words.
+    type State: Copy + Debug + Eq + AsRef<[Self::Word]> + AsMut<[Self::Word]>;
+    type Len: Length;
+    /// The digest, which is the state in bytes, maybe truncated.
+    type Digest: AsRef<[u8]> + for<'a> TryFrom<&'a [u8], Error = TryFromSliceError>;
+
+    /// Size of the blocks the compression function takes, in bytes.
+    const BLOCK_SIZE: usize;
+    /// Size of a digest in bytes.
+    const DIGEST_SIZE: usize;
+    const ENDIAN: Endian;
+    /// The state before any input.
+    const INITIAL_STATE: Self::State;
+
+    /// Mix one block, which is always [`Core::BLOCK_SIZE`] bytes, into the
+    /// state.
+    fn compress(state: &mut Self::State, block: &[u8]);
+}
+
+/// A core whose digest is its whole state, so that a hash can be resumed
+/// from its digest. SHA-224 and SHA-384 are truncated, so they aren't.
+pub trait Untruncated: Core {}
+
+/// A hash in progress.
+///
+/// The internal state is exposed, so that a hash can be resumed from a
+/// digest: this is what makes length extension possible.
+#[derive(Debug, Clone)]
+pub struct MerkleDamgard<C: Core> {
+    state: C::State,
+    /// Input not yet compressed, always shorter than a block.
+    buf: Vec<u8>,
+    /// Total bytes of input so far, including any before a resumed state.
+    len: C::Len,
+    core: PhantomData<C>,
+}
+
+impl<C: Core> Default for MerkleDamgard<C> {
+    fn default() -> Self {
+        MerkleDamgard::new()
+    }
+}
+
+impl<C: Core> MerkleDamgard<C> {
+    pub fn new() -> MerkleDamgard<C> {
+        MerkleDamgard::from_state(C::INITIAL_STATE, 0.into())
+    }
+
+    /// Resume a hash from its state after `len` bytes of input, such as the
+    /// state from [`MerkleDamgard::state_from_digest`] and the length of the
+    /// padded message it was the digest of.
+    ///
+    /// Panics if `len` is not a whole number of blocks.
+    pub fn from_state(state: C::State, len: C::Len) -> MerkleDamgard<C> {
+        assert!(
+            len.into().is_multiple_of(C::BLOCK_SIZE as u128),
+            "can't resume after {len} bytes, which is not a whole number of blocks"
+        );
+        MerkleDamgard {
+            state,
+            buf: Vec::with_capacity(C::BLOCK_SIZE),
+            len,
+            core: PhantomData,
+        }
+    }
+
+    /// The state after the last whole block of input.
+    pub fn state(&self) -> C::State {
+        self.state
+    }
+
+    /// The number of bytes of input so far.
+    pub fn len(&self) -> C::Len {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len.into() == 0
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len = self.len.wrapping_add_bytes(data.len());
+        if !self.buf.is_empty() {
+            let take = (C::BLOCK_SIZE - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buf.len() < C::BLOCK_SIZE {
+                return;
+            }
+            C::compress(&mut self.state, &self.buf);
+            self.buf.clear();
+        }
+        let mut blocks = data.chunks_exact(C::BLOCK_SIZE);
+        for block in &mut blocks {
+            C::compress(&mut self.state, block);
+        }
+        self.buf.extend_from_slice(blocks.remainder());
+    }
+
+    pub fn finalize(mut self) -> C::Digest {
+        let pad = Self::padding(self.len);
+        self.update(&pad);
+        debug_assert!(self.buf.is_empty());
+        let words = self.state.as_ref();
+        let word_size = size_of::<C::Word>();
+        let mut digest = vec![0u8; size_of_val(words)];
+        for (d, w) in digest.chunks_exact_mut(word_size).zip(words) {
+            w.write_bytes(d, C::ENDIAN);
+        }
+        digest[..C::DIGEST_SIZE].try_into().unwrap()
+    }
+
+    /// The padding appended to a message of `len` bytes before the last
+    /// compression: a 1 bit, zeros up to the size of the length short of a
+    /// block boundary, and then the length in bits.
+    pub fn padding(len: C::Len) -> Vec<u8> {
+        let len_size = size_of::<C::Len>();
+        let len: u128 = len.into();
+        let partial = (len % C::BLOCK_SIZE as u128) as usize;
+        let zeros = (2 * C::BLOCK_SIZE - 1 - len_size - partial) % C::BLOCK_SIZE;
+        let mut pad = Vec::with_capacity(1 + zeros + len_size);
+        pad.push(0x80);
+        pad.resize(1 + zeros, 0);
+        let bits = len.wrapping_mul(8);
+        match C::ENDIAN {
+            Endian::Big => pad.extend_from_slice(&bits.to_be_bytes()[16 - len_size..]),
+            Endian::Little => pad.extend_from_slice(&bits.to_le_bytes()[..len_size]),
+        }
+        pad
+    }
+
+    /// Recover the final state from a digest.
+    ///
+    /// Panics if the digest doesn't hold the whole state, which is the case
+    /// for the truncated hashes, SHA-224 and SHA-384.
+    pub fn state_from_digest(digest: &[u8]) -> C::State {
+        let mut state = C::INITIAL_STATE;
+        let word_size = size_of::<C::Word>();
+        assert_eq!(
+            digest.len(),
+            size_of_val(state.as_ref()),
+            "digest doesn't hold the whole state"
+        );
+        for (s, d) in state
+            .as_mut()
+            .iter_mut()
+            .zip(digest.chunks_exact(word_size))
+        {
+            *s = C::Word::from_bytes(d, C::ENDIAN);
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::md4::Md4;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha224;
+    use crate::sha512::Sha512;
+
+    #[test]
+    fn length_in_padding() {
+        assert_eq!(&Sha1::padding(3)[53..], &[0, 0, 0, 0, 0, 0, 0, 24]);
+        assert_eq!(&Md4::padding(3)[53..], &[24, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&Sha512::padding(3)[119..], &[0, 0, 0, 0, 0, 24]);
+        // The length in bits wraps.
+        assert_eq!(&Sha1::padding(1 << 61)[56..], &[0; 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "whole state")]
+    fn truncated_digest_has_no_state() {
+        Sha224::state_from_digest(&[0; 28]);
+    }
+}
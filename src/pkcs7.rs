@@ -2,21 +2,30 @@
 
 use std::iter;
 
+use crate::{Error, Result};
+
 /// Add padding in place in a buffer.
 ///
 /// The result will be an even multiple of sz bytes long.
 #[must_use]
 pub fn pad(b: &[u8], sz: usize) -> Vec<u8> {
-    assert!(sz < 256);
-    assert!(sz > 0);
+    try_pad(b, sz).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Add padding, or return an error if the block size is not between 1 and
+/// 255.
+pub fn try_pad(b: &[u8], sz: usize) -> Result<Vec<u8>> {
+    if sz == 0 || sz > 255 {
+        return Err(Error::BadBlockSize(sz));
+    }
     let mut m = sz - (b.len() % sz);
     if m == 0 {
         m = sz
     }
     let mut padded = Vec::with_capacity(b.len() + m);
     padded.extend_from_slice(b);
-    padded.extend(iter::repeat(m as u8).take(m));
-    padded
+    padded.extend(iter::repeat_n(m as u8, m));
+    Ok(padded)
 }
 
 /// Validate PKCS#7 padding and return a slice with it removed, if it's valid.
@@ -41,6 +50,12 @@ pub fn unpad(b: &[u8]) -> Option<&[u8]> {
     }
 }
 
+/// Validate PKCS#7 padding and return a slice with it removed, or
+/// [Error::BadPadding].
+pub fn try_unpad(b: &[u8]) -> Result<&[u8]> {
+    unpad(b).ok_or(Error::BadPadding)
+}
+
 #[cfg(test)]
 mod test {
     use proptest::prelude::*;
@@ -75,6 +90,15 @@ mod test {
         assert_eq!(unpad(&[2, 2]), Some(&[] as &[u8]));
     }
 
+    #[test]
+    fn fallible_variants() {
+        assert_eq!(try_pad(b"abc", 0), Err(Error::BadBlockSize(0)));
+        assert_eq!(try_pad(b"abc", 256), Err(Error::BadBlockSize(256)));
+        assert_eq!(try_pad(b"abc", 4).unwrap(), [b'a', b'b', b'c', 1]);
+        assert_eq!(try_unpad(&[0, 1, 2, 3u8]), Err(Error::BadPadding));
+        assert_eq!(try_unpad(&[9, 1]).unwrap(), [9]);
+    }
+
     proptest! {
         #[test]
         fn pad_roundtrip(b: Vec<u8>, blk in 1..20usize) {
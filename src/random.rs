@@ -1,15 +1,366 @@
-//! Random number helpers.
+//! Random number helpers, and the Mersenne Twister.
+//!
+//! The Mersenne Twister is not cryptographically secure: its state can be
+//! recovered from its output. It's here to be attacked, in
+//! <https://cryptopals.com/sets/3>.
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use rand::prelude::*;
+use rand::Error as RandError;
 
 /// Return a vector of `n` random bytes.
 pub fn random_bytes(n: usize) -> Vec<u8> {
-    let mut rng = thread_rng();
-    (0..n).map(|_| rng.gen()).collect()
+    random_bytes_from(&mut thread_rng(), n)
+}
+
+/// Return a vector of `n` random bytes from `rng`.
+///
+/// Passing a seeded RNG, rather than using [`random_bytes`], makes a test
+/// that fails reproducible from its seed.
+pub fn random_bytes_from<R: RngCore + ?Sized>(rng: &mut R, n: usize) -> Vec<u8> {
+    let mut bytes = vec![0; n];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// A source of the current time, in seconds since the Unix epoch.
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+/// The real time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time before the epoch")
+            .as_secs()
+    }
+}
+
+/// A clock that only moves when told to, so that tests can pretend to wait.
+pub struct FakeClock {
+    now: Cell<u64>,
+}
+
+impl FakeClock {
+    pub fn new(now: u64) -> FakeClock {
+        FakeClock {
+            now: Cell::new(now),
+        }
+    }
+
+    /// Move the clock forward by `secs` seconds.
+    pub fn advance(&self, secs: u64) {
+        self.now.set(self.now.get() + secs);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+/// The number of words of MT19937 state.
+pub const MT_N: usize = 624;
+const MT_M: usize = 397;
+const MT_MATRIX_A: u32 = 0x9908_b0df;
+const MT_UPPER: u32 = 0x8000_0000;
+const MT_LOWER: u32 = 0x7fff_ffff;
+
+/// The 32-bit Mersenne Twister, MT19937, as in the reference `mt19937ar.c`.
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; MT_N],
+    index: usize,
+}
+
+impl Mt19937 {
+    /// Seed the generator from one word, like `init_genrand`.
+    pub fn new(seed: u32) -> Mt19937 {
+        let mut state = [0u32; MT_N];
+        state[0] = seed;
+        for i in 1..MT_N {
+            let prev = state[i - 1];
+            state[i] = 1_812_433_253u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: MT_N }
+    }
+
+    /// Seed the generator from an array of words, like `init_by_array`.
+    ///
+    /// Panics if the key is empty.
+    pub fn from_key(key: &[u32]) -> Mt19937 {
+        assert!(!key.is_empty(), "empty MT19937 key");
+        let mut mt = Mt19937::new(19_650_218);
+        let s = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..MT_N.max(key.len()) {
+            let prev = s[i - 1];
+            s[i] = (s[i] ^ (prev ^ (prev >> 30)).wrapping_mul(1_664_525))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= MT_N {
+                s[0] = s[MT_N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..MT_N - 1 {
+            let prev = s[i - 1];
+            s[i] =
+                (s[i] ^ (prev ^ (prev >> 30)).wrapping_mul(1_566_083_941)).wrapping_sub(i as u32);
+            i += 1;
+            if i >= MT_N {
+                s[0] = s[MT_N - 1];
+                i = 1;
+            }
+        }
+        s[0] = MT_UPPER;
+        mt
+    }
+
+    /// Seed the generator with the current time in seconds, as a careless
+    /// program might.
+    pub fn from_time(clock: &dyn Clock) -> Mt19937 {
+        Mt19937::new(clock.now() as u32)
+    }
+
+    /// Make a generator from its state, as it is just before generating new
+    /// state, so that the next output comes from `state[0]` after a twist.
+    pub fn from_state(state: [u32; MT_N]) -> Mt19937 {
+        Mt19937 { state, index: MT_N }
+    }
+
+    /// Generate the next block of state.
+    fn twist(&mut self) {
+        let s = &mut self.state;
+        for i in 0..MT_N {
+            let x = (s[i] & MT_UPPER) | (s[(i + 1) % MT_N] & MT_LOWER);
+            let mut xa = x >> 1;
+            if x & 1 != 0 {
+                xa ^= MT_MATRIX_A;
+            }
+            s[i] = s[(i + MT_M) % MT_N] ^ xa;
+        }
+        self.index = 0;
+    }
+}
+
+/// Scramble a word of MT19937 state into an output.
+pub fn mt_temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^ (y >> 18)
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= MT_N {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        mt_temper(y)
+    }
+
+    /// Two outputs, the first in the low half.
+    fn next_u64(&mut self) -> u64 {
+        let lo = self.next_u32();
+        u64::from(self.next_u32()) << 32 | u64::from(lo)
+    }
+
+    /// Fill with little-endian outputs, dropping the end of the last one.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937 {
+    /// A little-endian `u32` seed.
+    type Seed = [u8; 4];
+
+    fn from_seed(seed: Self::Seed) -> Mt19937 {
+        Mt19937::new(u32::from_le_bytes(seed))
+    }
+
+    /// Use the low 32 bits of the number as the seed as is, rather than
+    /// scrambling it, so that this agrees with [Mt19937::new], as it does
+    /// for [Mt19937_64].
+    fn seed_from_u64(state: u64) -> Mt19937 {
+        Mt19937::new(state as u32)
+    }
+}
+
+/// Encrypt or decrypt with the little-endian bytes of MT19937 outputs as a
+/// keystream, seeded with a 16-bit key.
+///
+/// From <https://cryptopals.com/sets/3/challenges/24>; a 16-bit key is of
+/// course trivial to brute force.
+#[must_use]
+pub fn mt_stream_xor(text: &[u8], seed: u16) -> Vec<u8> {
+    let mut keystream = vec![0; text.len()];
+    Mt19937::new(seed.into()).fill_bytes(&mut keystream);
+    text.iter().zip(keystream).map(|(a, b)| a ^ b).collect()
+}
+
+/// The number of words of MT19937-64 state.
+pub const MT64_N: usize = 312;
+const MT64_M: usize = 156;
+const MT64_MATRIX_A: u64 = 0xb502_6f5a_a966_19e9;
+const MT64_UPPER: u64 = 0xffff_ffff_8000_0000;
+const MT64_LOWER: u64 = 0x7fff_ffff;
+
+/// The 64-bit Mersenne Twister, MT19937-64, as in the reference
+/// `mt19937-64.c`.
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; MT64_N],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    /// Seed the generator from one word, like `init_genrand64`.
+    pub fn new(seed: u64) -> Mt19937_64 {
+        let mut state = [0u64; MT64_N];
+        state[0] = seed;
+        for i in 1..MT64_N {
+            let prev = state[i - 1];
+            state[i] = 6_364_136_223_846_793_005u64
+                .wrapping_mul(prev ^ (prev >> 62))
+                .wrapping_add(i as u64);
+        }
+        Mt19937_64 {
+            state,
+            index: MT64_N,
+        }
+    }
+
+    /// Seed the generator from an array of words, like `init_by_array64`.
+    ///
+    /// Panics if the key is empty.
+    pub fn from_key(key: &[u64]) -> Mt19937_64 {
+        assert!(!key.is_empty(), "empty MT19937-64 key");
+        let mut mt = Mt19937_64::new(19_650_218);
+        let s = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..MT64_N.max(key.len()) {
+            let prev = s[i - 1];
+            s[i] = (s[i] ^ (prev ^ (prev >> 62)).wrapping_mul(3_935_559_000_370_003_845))
+                .wrapping_add(key[j])
+                .wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= MT64_N {
+                s[0] = s[MT64_N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..MT64_N - 1 {
+            let prev = s[i - 1];
+            s[i] = (s[i] ^ (prev ^ (prev >> 62)).wrapping_mul(2_862_933_555_777_941_757))
+                .wrapping_sub(i as u64);
+            i += 1;
+            if i >= MT64_N {
+                s[0] = s[MT64_N - 1];
+                i = 1;
+            }
+        }
+        s[0] = 1 << 63;
+        mt
+    }
+
+    /// Generate the next block of state.
+    fn twist(&mut self) {
+        let s = &mut self.state;
+        for i in 0..MT64_N {
+            let x = (s[i] & MT64_UPPER) | (s[(i + 1) % MT64_N] & MT64_LOWER);
+            let mut xa = x >> 1;
+            if x & 1 != 0 {
+                xa ^= MT64_MATRIX_A;
+            }
+            s[i] = s[(i + MT64_M) % MT64_N] ^ xa;
+        }
+        self.index = 0;
+    }
+}
+
+/// Scramble a word of MT19937-64 state into an output.
+pub fn mt64_temper(mut x: u64) -> u64 {
+    x ^= (x >> 29) & 0x5555_5555_5555_5555;
+    x ^= (x << 17) & 0x71d6_7fff_eda6_0000;
+    x ^= (x << 37) & 0xfff7_eee0_0000_0000;
+    x ^ (x >> 43)
+}
+
+impl RngCore for Mt19937_64 {
+    /// The low half of the next 64-bit output.
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.index >= MT64_N {
+            self.twist();
+        }
+        let x = self.state[self.index];
+        self.index += 1;
+        mt64_temper(x)
+    }
+
+    /// Fill with little-endian outputs, dropping the end of the last one.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937_64 {
+    /// A little-endian `u64` seed.
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Mt19937_64 {
+        Mt19937_64::new(u64::from_le_bytes(seed))
+    }
+
+    /// Use the number as the seed as is, rather than scrambling it.
+    fn seed_from_u64(state: u64) -> Mt19937_64 {
+        Mt19937_64::new(state)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -19,4 +370,110 @@ mod test {
             assert_eq!(r.len(), n);
         }
     }
+
+    /// The default seed, from the C++ standard's `mt19937` requirements.
+    #[test]
+    fn mt19937_default_seed() {
+        let mut mt = Mt19937::new(5489);
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(
+            first,
+            [3499211612, 581869302, 3890346734, 3586334585, 545404204]
+        );
+        let mut mt = Mt19937::seed_from_u64(5489);
+        let last = (0..10000).map(|_| mt.next_u32()).last();
+        assert_eq!(last, Some(4123659995));
+    }
+
+    /// The first outputs of `mt19937ar.out`.
+    #[test]
+    fn mt19937_init_by_array() {
+        let mut mt = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(
+            first,
+            [1067595299, 955945823, 477289528, 4107218783, 4228976476]
+        );
+    }
+
+    /// The default seed, from the C++ standard's `mt19937_64` requirements.
+    #[test]
+    fn mt19937_64_default_seed() {
+        let mut mt = Mt19937_64::new(5489);
+        assert_eq!(mt.next_u64(), 14514284786278117030);
+        let mut mt = Mt19937_64::seed_from_u64(5489);
+        let last = (0..10000).map(|_| mt.next_u64()).last();
+        assert_eq!(last, Some(9981545732273789042));
+    }
+
+    /// The first outputs of `mt19937-64.out.txt`.
+    #[test]
+    fn mt19937_64_init_by_array() {
+        let mut mt = Mt19937_64::from_key(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let first: Vec<u64> = (0..5).map(|_| mt.next_u64()).collect();
+        assert_eq!(
+            first,
+            [
+                7266447313870364031,
+                4946485549665804864,
+                16945909448695747420,
+                16394063075524226720,
+                4873882236456199058
+            ]
+        );
+    }
+
+    /// The generators work anywhere a `rand` RNG is wanted.
+    #[test]
+    fn usable_as_rng() {
+        let mut mt = Mt19937::from_seed(5489u32.to_le_bytes());
+        assert_eq!(mt.gen::<u32>(), 3499211612);
+        let mut bytes = [0u8; 7];
+        mt.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..4], 581869302u32.to_le_bytes());
+        let x: usize = Mt19937_64::seed_from_u64(1).gen_range(10..20);
+        assert!((10..20).contains(&x));
+        assert!([1, 2, 3].choose(&mut Mt19937::new(7)).is_some());
+    }
+
+    #[test]
+    fn fake_clock() {
+        let clock = FakeClock::new(1_000_000);
+        clock.advance(40);
+        assert_eq!(clock.now(), 1_000_040);
+        assert_eq!(
+            Mt19937::from_time(&clock).next_u32(),
+            Mt19937::new(1_000_040).next_u32()
+        );
+        assert!(SystemClock.now() > 1_600_000_000);
+    }
+
+    proptest! {
+        #[test]
+        fn seeded_random_bytes_repeat(seed: u64) {
+            let a = random_bytes_from(&mut StdRng::seed_from_u64(seed), 40);
+            let b = random_bytes_from(&mut StdRng::seed_from_u64(seed), 40);
+            assert_eq!(a, b);
+            let c = random_bytes_from(&mut Mt19937_64::new(seed), 40);
+            assert_ne!(a, c);
+        }
+
+        #[test]
+        fn mt_stream_roundtrip(seed: u16, plain: Vec<u8>) {
+            let ct = mt_stream_xor(&plain, seed);
+            assert_eq!(mt_stream_xor(&ct, seed), plain);
+        }
+
+        #[test]
+        fn seed_from_u64_matches_new(seed: u64) {
+            let (mut a, mut b) = (Mt19937::seed_from_u64(seed), Mt19937::new(seed as u32));
+            for _ in 0..700 {
+                assert_eq!(a.next_u32(), b.next_u32());
+            }
+            let (mut a, mut b) = (Mt19937_64::seed_from_u64(seed), Mt19937_64::new(seed));
+            for _ in 0..400 {
+                assert_eq!(a.next_u64(), b.next_u64());
+            }
+        }
+    }
 }
@@ -0,0 +1,323 @@
+//! The AES block cipher, written out by hand from FIPS-197.
+//!
+//! This is a straightforward byte-oriented implementation, without lookup
+//! tables beyond the S-box, and is not constant-time. It can be used as the
+//! backend for [crate::aes] by enabling the `rijndael` feature.
+
+use lazy_static::lazy_static;
+
+use crate::block::BlockCipher;
+use crate::{Error, Result};
+
+/// Size of the AES block in bytes.
+pub const BLOCKSIZE: usize = 16;
+
+lazy_static! {
+    static ref SBOX: [u8; 256] = make_sbox();
+    static ref INV_SBOX: [u8; 256] = {
+        let mut inv = [0u8; 256];
+        for (i, &s) in SBOX.iter().enumerate() {
+            inv[s as usize] = i as u8;
+        }
+        inv
+    };
+}
+
+/// Multiply by x (that is, by 2) in GF(2^8) modulo the AES polynomial
+/// x^8 + x^4 + x^3 + x + 1.
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Multiply two elements of GF(2^8).
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            r ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    r
+}
+
+/// Multiplicative inverse in GF(2^8), with 0 mapping to 0.
+fn ginv(a: u8) -> u8 {
+    // a^254 = a^-1, since the multiplicative group has order 255.
+    let mut r = 1;
+    for _ in 0..254 {
+        r = gmul(r, a);
+    }
+    r
+}
+
+/// Build the S-box from the field inverse followed by the affine transform.
+fn make_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    for (i, s) in sbox.iter_mut().enumerate() {
+        let b = ginv(i as u8);
+        *s = b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+    }
+    sbox
+}
+
+/// An expanded AES key, of 128, 192 or 256 bits.
+#[derive(Clone)]
+pub struct Rijndael {
+    /// One 16-byte round key for the initial whitening and for each round.
+    round_keys: Vec<[u8; BLOCKSIZE]>,
+}
+
+impl Rijndael {
+    /// Expand a 16, 24 or 32-byte key.
+    pub fn new(key: &[u8]) -> Result<Rijndael> {
+        if ![16, 24, 32].contains(&key.len()) {
+            return Err(Error::BadKeyLength(key.len()));
+        }
+        let nk = key.len() / 4;
+        let nr = nk + 6;
+        let n_words = 4 * (nr + 1);
+        let mut w: Vec<[u8; 4]> = key.chunks_exact(4).map(|c| c.try_into().unwrap()).collect();
+        let mut rcon = 1u8;
+        for i in nk..n_words {
+            let mut t = w[i - 1];
+            if i % nk == 0 {
+                t.rotate_left(1);
+                t = t.map(|b| SBOX[b as usize]);
+                t[0] ^= rcon;
+                rcon = xtime(rcon);
+            } else if nk > 6 && i % nk == 4 {
+                t = t.map(|b| SBOX[b as usize]);
+            }
+            let prev = w[i - nk];
+            w.push([
+                t[0] ^ prev[0],
+                t[1] ^ prev[1],
+                t[2] ^ prev[2],
+                t[3] ^ prev[3],
+            ]);
+        }
+        let round_keys = w
+            .chunks_exact(4)
+            .map(|words| {
+                let mut rk = [0u8; BLOCKSIZE];
+                for (dst, word) in rk.chunks_exact_mut(4).zip(words) {
+                    dst.copy_from_slice(word);
+                }
+                rk
+            })
+            .collect();
+        Ok(Rijndael { round_keys })
+    }
+
+    /// The length of the original key in bytes.
+    pub fn key_len(&self) -> usize {
+        // There are Nr+1 round keys, and Nr = Nk + 6.
+        (self.round_keys.len() - 7) * 4
+    }
+
+    fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+}
+
+impl BlockCipher for Rijndael {
+    fn block_size(&self) -> usize {
+        BLOCKSIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), BLOCKSIZE);
+        let nr = self.rounds();
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..nr {
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+        sub_bytes(block);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[nr]);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), BLOCKSIZE);
+        let nr = self.rounds();
+        add_round_key(block, &self.round_keys[nr]);
+        for round in (1..nr).rev() {
+            inv_shift_rows(block);
+            inv_sub_bytes(block);
+            add_round_key(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+        inv_shift_rows(block);
+        inv_sub_bytes(block);
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+// The state is kept in the same order as the input bytes, which is column-major:
+// byte `r + 4 * c` is row `r` of column `c`.
+
+fn add_round_key(state: &mut [u8], round_key: &[u8; BLOCKSIZE]) {
+    for (s, k) in state.iter_mut().zip(round_key) {
+        *s ^= k;
+    }
+}
+
+fn sub_bytes(state: &mut [u8]) {
+    for s in state.iter_mut() {
+        *s = SBOX[*s as usize];
+    }
+}
+
+fn inv_sub_bytes(state: &mut [u8]) {
+    for s in state.iter_mut() {
+        *s = INV_SBOX[*s as usize];
+    }
+}
+
+/// Rotate row `r` left by `r` columns.
+fn shift_rows(state: &mut [u8]) {
+    let old: [u8; BLOCKSIZE] = state.try_into().unwrap();
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * c] = old[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+/// Rotate row `r` right by `r` columns.
+fn inv_shift_rows(state: &mut [u8]) {
+    let old: [u8; BLOCKSIZE] = state.try_into().unwrap();
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = old[r + 4 * c];
+        }
+    }
+}
+
+/// Multiply each column by the fixed polynomial {03}x^3 + {01}x^2 + {01}x + {02}.
+fn mix_columns(state: &mut [u8]) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = xtime(a0) ^ gmul(a1, 3) ^ a2 ^ a3;
+        col[1] = a0 ^ xtime(a1) ^ gmul(a2, 3) ^ a3;
+        col[2] = a0 ^ a1 ^ xtime(a2) ^ gmul(a3, 3);
+        col[3] = gmul(a0, 3) ^ a1 ^ a2 ^ xtime(a3);
+    }
+}
+
+/// Multiply each column by the inverse polynomial {0b}x^3 + {0d}x^2 + {09}x + {0e}.
+fn inv_mix_columns(state: &mut [u8]) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 0x0e) ^ gmul(a1, 0x0b) ^ gmul(a2, 0x0d) ^ gmul(a3, 0x09);
+        col[1] = gmul(a0, 0x09) ^ gmul(a1, 0x0e) ^ gmul(a2, 0x0b) ^ gmul(a3, 0x0d);
+        col[2] = gmul(a0, 0x0d) ^ gmul(a1, 0x09) ^ gmul(a2, 0x0e) ^ gmul(a3, 0x0b);
+        col[3] = gmul(a0, 0x0b) ^ gmul(a1, 0x0d) ^ gmul(a2, 0x09) ^ gmul(a3, 0x0e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aes::cipher::generic_array::GenericArray;
+    use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::hex_to_bytes;
+
+    #[test]
+    fn sbox_values() {
+        assert_eq!(SBOX[0x00], 0x63);
+        assert_eq!(SBOX[0x01], 0x7c);
+        assert_eq!(SBOX[0x53], 0xed);
+        assert_eq!(SBOX[0xff], 0x16);
+        assert_eq!(INV_SBOX[0x63], 0x00);
+        assert_eq!(INV_SBOX[0xed], 0x53);
+    }
+
+    #[test]
+    fn field_multiplication() {
+        // FIPS-197 section 4.2.
+        assert_eq!(gmul(0x57, 0x83), 0xc1);
+        assert_eq!(gmul(0x57, 0x13), 0xfe);
+        for a in 1..=255u8 {
+            assert_eq!(gmul(a, ginv(a)), 1);
+        }
+    }
+
+    /// FIPS-197 Appendix A.1: the last round key for a 128-bit key.
+    #[test]
+    fn key_expansion_128() {
+        let r = Rijndael::new(&hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        assert_eq!(r.round_keys.len(), 11);
+        assert_eq!(
+            r.round_keys[10].as_slice(),
+            hex_to_bytes("d014f9a8c9ee2589e13f0cc8b6630ca6")
+        );
+    }
+
+    /// FIPS-197 Appendix C: one block encrypted under each key size.
+    #[test]
+    fn fips197_examples() {
+        let plain = hex_to_bytes("00112233445566778899aabbccddeeff");
+        for (key_len, expected) in [
+            (16, "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            (24, "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            (32, "8ea2b7ca516745bfeafc49904b496089"),
+        ] {
+            let key: Vec<u8> = (0..key_len as u8).collect();
+            let r = Rijndael::new(&key).unwrap();
+            assert_eq!(r.key_len(), key_len);
+            let mut block = plain.to_vec();
+            r.encrypt_block(&mut block);
+            assert_eq!(block, hex_to_bytes(expected));
+            r.decrypt_block(&mut block);
+            assert_eq!(block, plain);
+        }
+    }
+
+    #[test]
+    fn bad_key_length() {
+        assert_eq!(Rijndael::new(&[0; 20]).err(), Some(Error::BadKeyLength(20)));
+    }
+
+    fn any_key() -> impl Strategy<Value = Vec<u8>> {
+        prop::sample::select(vec![16, 24, 32])
+            .prop_flat_map(|l| prop::collection::vec(any::<u8>(), l))
+    }
+
+    proptest! {
+        /// Encryption matches the `aes` crate for every key size.
+        #[test]
+        fn encrypt_matches_aes_crate(key in any_key(), block: [u8; 16]) {
+            let mut ours = block;
+            Rijndael::new(&key).unwrap().encrypt_block(&mut ours);
+            let mut theirs = GenericArray::from(block);
+            match key.len() {
+                16 => aes::Aes128::new_from_slice(&key).unwrap().encrypt_block(&mut theirs),
+                24 => aes::Aes192::new_from_slice(&key).unwrap().encrypt_block(&mut theirs),
+                _ => aes::Aes256::new_from_slice(&key).unwrap().encrypt_block(&mut theirs),
+            }
+            assert_eq!(ours.as_slice(), theirs.as_slice());
+        }
+
+        /// Decryption matches the `aes` crate for every key size.
+        #[test]
+        fn decrypt_matches_aes_crate(key in any_key(), block: [u8; 16]) {
+            let mut ours = block;
+            Rijndael::new(&key).unwrap().decrypt_block(&mut ours);
+            let mut theirs = GenericArray::from(block);
+            match key.len() {
+                16 => aes::Aes128::new_from_slice(&key).unwrap().decrypt_block(&mut theirs),
+                24 => aes::Aes192::new_from_slice(&key).unwrap().decrypt_block(&mut theirs),
+                _ => aes::Aes256::new_from_slice(&key).unwrap().decrypt_block(&mut theirs),
+            }
+            assert_eq!(ours.as_slice(), theirs.as_slice());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! SHA-1, written out by hand from FIPS 180-4.
+//!
+//! The internal state is exposed, so that a hash can be resumed from a
+//! digest: this is what makes length extension possible. SHA-1 is broken
+//! for collisions and is here to be attacked.
+
+use crate::merkle_damgard::{Core, Endian, MerkleDamgard, Untruncated};
+
+/// Size of the blocks the compression function takes, in bytes.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Size of a digest in bytes.
+pub const DIGEST_SIZE: usize = 20;
+
+/// The state before any input, from FIPS 180-4 section 5.3.1.
+pub const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// The SHA-1 compression function, with big-endian 32-bit words.
+#[derive(Debug, Clone)]
+pub struct Sha1Core;
+
+impl Core for Sha1Core {
+    type Word = u32;
+    type State = [u32; 5];
+    type Len = u64;
+    type Digest = [u8; DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Big;
+    const INITIAL_STATE: [u32; 5] = INITIAL_STATE;
+
+    fn compress(state: &mut [u32; 5], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+impl Untruncated for Sha1Core {}
+
+/// A SHA-1 hash in progress.
+pub type Sha1 = MerkleDamgard<Sha1Core>;
+
+/// Hash `data` in one go.
+pub fn sha1(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Sha1::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Mix one block into the state, as in FIPS 180-4 section 6.1.2.
+fn compress(state: &mut [u32; 5], block: &[u8; BLOCK_SIZE]) {
+    let mut w = [0u32; 80];
+    for (w, b) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *w = u32::from_be_bytes(b.try_into().unwrap());
+    }
+    for t in 16..80 {
+        w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (t, &w) in w.iter().enumerate() {
+        let (f, k) = match t {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(w);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::bytes_to_hex;
+
+    /// FIPS 180 examples, and the other NIST short vectors.
+    #[test]
+    fn fips180_vectors() {
+        for (input, expected) in [
+            ("", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            ("abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+            (
+                "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+                "a49b2446a02c645bf419f995b67091253a04a259",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha1(input.as_bytes())), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn million_a() {
+        let mut h = Sha1::new();
+        for _ in 0..1000 {
+            h.update(&[b'a'; 1000]);
+        }
+        assert_eq!(
+            bytes_to_hex(&h.finalize()),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+        );
+    }
+
+    #[test]
+    fn padding_fills_to_a_block() {
+        assert_eq!(Sha1::padding(0).len(), 64);
+        assert_eq!(Sha1::padding(55).len(), 9);
+        assert_eq!(Sha1::padding(56).len(), 72);
+        assert_eq!(Sha1::padding(64).len(), 64);
+        assert_eq!(&Sha1::padding(3)[53..], &[0, 0, 0, 0, 0, 0, 0, 24]);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(data: Vec<u8>, splits: Vec<usize>) {
+            let mut h = Sha1::new();
+            let mut rest = &data[..];
+            for s in splits {
+                let (a, b) = rest.split_at(s % (rest.len() + 1));
+                h.update(a);
+                rest = b;
+            }
+            h.update(rest);
+            assert_eq!(h.len(), data.len() as u64);
+            assert_eq!(h.finalize(), sha1(&data));
+        }
+
+        /// Hashing on from a digest gives the hash of the padded message
+        /// plus whatever comes next.
+        #[test]
+        fn resume_from_digest(msg: Vec<u8>, more: Vec<u8>) {
+            let glued_len = (msg.len() + Sha1::padding(msg.len() as u64).len()) as u64;
+            let mut h = Sha1::from_state(Sha1::state_from_digest(&sha1(&msg)), glued_len);
+            h.update(&more);
+            let glued = [&msg[..], &Sha1::padding(msg.len() as u64), &more].concat();
+            assert_eq!(h.finalize(), sha1(&glued));
+        }
+    }
+}
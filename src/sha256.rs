@@ -0,0 +1,326 @@
+//! SHA-256 and SHA-224, written out by hand from FIPS 180-4.
+//!
+//! Like [crate::sha1], the internal state is exposed so that a hash can be
+//! resumed from a digest. SHA-224 is SHA-256 with a different initial state
+//! and a truncated digest, so a SHA-224 digest doesn't give away the whole
+//! state.
+
+use crate::merkle_damgard::{Core, Endian, MerkleDamgard, Untruncated};
+
+/// Size of the blocks the compression function takes, in bytes.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Size of a SHA-256 digest in bytes.
+pub const DIGEST_SIZE: usize = 32;
+
+/// Size of a SHA-224 digest in bytes.
+pub const SHA224_DIGEST_SIZE: usize = 28;
+
+/// The SHA-256 state before any input, from FIPS 180-4 section 5.3.3.
+pub const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The SHA-224 state before any input, from FIPS 180-4 section 5.3.2.
+pub const SHA224_INITIAL_STATE: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+/// The round constants: the first 32 bits of the fractional parts of the
+/// cube roots of the first 64 primes.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 compression function, with big-endian 32-bit words.
+#[derive(Debug, Clone)]
+pub struct Sha256Core;
+
+impl Core for Sha256Core {
+    type Word = u32;
+    type State = [u32; 8];
+    type Len = u64;
+    type Digest = [u8; DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Big;
+    const INITIAL_STATE: [u32; 8] = INITIAL_STATE;
+
+    fn compress(state: &mut [u32; 8], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+impl Untruncated for Sha256Core {}
+
+/// A SHA-256 hash in progress.
+pub type Sha256 = MerkleDamgard<Sha256Core>;
+
+/// SHA-256 from a different initial state, with the digest truncated to 28
+/// bytes.
+#[derive(Debug, Clone)]
+pub struct Sha224Core;
+
+impl Core for Sha224Core {
+    type Word = u32;
+    type State = [u32; 8];
+    type Len = u64;
+    type Digest = [u8; SHA224_DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = SHA224_DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Big;
+    const INITIAL_STATE: [u32; 8] = SHA224_INITIAL_STATE;
+
+    fn compress(state: &mut [u32; 8], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+/// A SHA-224 hash in progress.
+pub type Sha224 = MerkleDamgard<Sha224Core>;
+
+/// Hash `data` with SHA-256 in one go.
+pub fn sha256(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Sha256::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Hash `data` with SHA-224 in one go.
+pub fn sha224(data: &[u8]) -> [u8; SHA224_DIGEST_SIZE] {
+    let mut h = Sha224::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Mix one block into the state, as in FIPS 180-4 section 6.2.2.
+fn compress(state: &mut [u32; 8], block: &[u8; BLOCK_SIZE]) {
+    let mut w = [0u32; 64];
+    for (w, b) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *w = u32::from_be_bytes(b.try_into().unwrap());
+    }
+    for t in 16..64 {
+        let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+        let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+        w[t] = w[t - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[t - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (&k, &w) in K.iter().zip(&w) {
+        let big_s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(big_s1)
+            .wrapping_add(ch)
+            .wrapping_add(k)
+            .wrapping_add(w);
+        let big_s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = big_s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{bytes_to_hex, hex_to_bytes};
+
+    const ABC_448: &str = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+    /// The NIST examples for FIPS 180, plus the empty message.
+    #[test]
+    fn fips180_vectors() {
+        for (input, sha224_hex, sha256_hex) in [
+            (
+                "",
+                "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                "abc",
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                ABC_448,
+                "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha224(input.as_bytes())), sha224_hex);
+            assert_eq!(bytes_to_hex(&sha256(input.as_bytes())), sha256_hex);
+        }
+    }
+
+    #[test]
+    fn million_a() {
+        let mut h224 = Sha224::new();
+        let mut h256 = Sha256::new();
+        for _ in 0..1000 {
+            h224.update(&[b'a'; 1000]);
+            h256.update(&[b'a'; 1000]);
+        }
+        assert_eq!(
+            bytes_to_hex(&h224.finalize()),
+            "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67"
+        );
+        assert_eq!(
+            bytes_to_hex(&h256.finalize()),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    /// A sample of the byte-oriented short messages from the NIST CAVP test
+    /// vectors, SHA224ShortMsg.rsp and SHA256ShortMsg.rsp, from
+    /// <https://csrc.nist.gov/projects/cryptographic-algorithm-validation-program/secure-hashing>.
+    #[test]
+    fn cavp_short_msg() {
+        for (msg, expected) in [
+            (
+                "84",
+                "3cd36921df5d6963e73739cf4d20211e2d8877c19cff087ade9d0e3a",
+            ),
+            (
+                "5c7b",
+                "daff9bce685eb831f97fc1225b03c275a6c112e2d6e76f5faf7a36e6",
+            ),
+            (
+                "51ca3d",
+                "2c8959023515476e38388abb43599a29876b4b33d56adc06032de3a2",
+            ),
+            (
+                "6084347e",
+                "ae57c0a6d49739ba338adfa53bdae063e5c09122b77604780a8eeaa3",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha224(&hex_to_bytes(msg))), expected, "{msg}");
+        }
+        for (msg, expected) in [
+            (
+                "d3",
+                "28969cdfa74a12c82f3bad960b0b000aca2ac329deea5c2328ebc6f2ba9802c1",
+            ),
+            (
+                "11af",
+                "5ca7133fa735326081558ac312c620eeca9970d1e70a4b95533d956f072d1f98",
+            ),
+            (
+                "b4190e",
+                "dff2e73091f6c05e528896c4c831b9448653dc2ff043528f6769437bc7b975c2",
+            ),
+            (
+                "74ba2521",
+                "b16aa56be3880d18cd41e68384cf1ec8c17680c45a02b1575dc1518923ae8b0e",
+            ),
+            (
+                "c299209682",
+                "f0887fe961c9cd3beab957e8222494abb969b1ce4c6557976df8b0f6d20e9166",
+            ),
+            (
+                "e1dc724d5621",
+                "eca0a060b489636225b4fa64d267dabbe44273067ac679f20820bddc6b6a90ac",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha256(&hex_to_bytes(msg))), expected, "{msg}");
+        }
+    }
+
+    /// The first long message from each of the NIST CAVP test vectors,
+    /// SHA224LongMsg.rsp and SHA256LongMsg.rsp, from the same page. They are
+    /// hashed both at once and in small pieces that straddle the
+    /// 64-byte blocks.
+    #[test]
+    fn cavp_long_msg() {
+        let msg = hex_to_bytes(
+            "f149e41d848f59276cfddd743bafa9a90e1ee4a263a118142b33e3702176ef0a\
+            59f8237a1cb51b42f3ded6b202d9af0997898fdd03cf60bda951c514547a0850\
+            cec25444ae2f24cb711bfbafcc3956c941d3de69f155e3f8b10f06db5f37359b\
+            772ddd43e1035a0a0d3db33242d5843033833b0dd43b870c6bf60e8deab55f31\
+            7cc3273f5e3ba747f0cb65050cb7228796210d9254873643008d45f29cfd6c5b\
+            060c9a",
+        );
+        let expected = "9db6dc3a23abd7b6c3d72c38f4843c7de48a71d0ba91a86b18393e5f";
+        assert_eq!(bytes_to_hex(&sha224(&msg)), expected);
+        let mut h = Sha224::new();
+        for piece in msg.chunks(7) {
+            h.update(piece);
+        }
+        assert_eq!(bytes_to_hex(&h.finalize()), expected);
+
+        let msg = hex_to_bytes(
+            "451101250ec6f26652249d59dc974b7361d571a8101cdfd36aba3b5854d3ae08\
+            6b5fdd4597721b66e3c0dc5d8c606d9657d0e323283a5217d1f53f2f284f57b8\
+            5c8a61ac8924711f895c5ed90ef17745ed2d728abd22a5f7a13479a462d71b56\
+            c19a74a40b655c58edfe0a188ad2cf46cbf30524f65d423c837dd1ff2bf462ac\
+            4198007345bb44dbb7b1c861298cdf61982a833afc728fae1eda2f87aa2c9480\
+            858bec",
+        );
+        let expected = "3c593aa539fdcdae516cdf2f15000f6634185c88f505b39775fb9ab137a10aa2";
+        assert_eq!(bytes_to_hex(&sha256(&msg)), expected);
+        let mut h = Sha256::new();
+        for piece in msg.chunks(7) {
+            h.update(piece);
+        }
+        assert_eq!(bytes_to_hex(&h.finalize()), expected);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(data: Vec<u8>, split: usize) {
+            let (a, b) = data.split_at(split % (data.len() + 1));
+            let mut h = Sha256::new();
+            h.update(a);
+            h.update(b);
+            assert_eq!(h.len(), data.len() as u64);
+            assert_eq!(h.finalize(), sha256(&data));
+        }
+
+        #[test]
+        fn resume_from_digest(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Sha256::padding(msg.len() as u64);
+            let mut h = Sha256::from_state(
+                Sha256::state_from_digest(&sha256(&msg)),
+                (msg.len() + pad.len()) as u64,
+            );
+            h.update(&more);
+            assert_eq!(h.finalize(), sha256(&[&msg[..], &pad, &more].concat()));
+        }
+
+        /// SHA-224 can be resumed from its full state, just not from its
+        /// digest.
+        #[test]
+        fn sha224_resume_from_state(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Sha256::padding(msg.len() as u64);
+            let mut h = Sha224::new();
+            h.update(&msg);
+            h.update(&pad);
+            let mut resumed = Sha224::from_state(h.state(), h.len());
+            resumed.update(&more);
+            assert_eq!(resumed.finalize(), sha224(&[&msg[..], &pad, &more].concat()));
+        }
+    }
+}
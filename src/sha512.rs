@@ -0,0 +1,417 @@
+//! SHA-512 and SHA-384, written out by hand from FIPS 180-4.
+//!
+//! These have the same shape as [SHA-256](crate::sha256), but with 64-bit
+//! words, 128-byte blocks, and a 128-bit length. Like SHA-224, SHA-384 is
+//! truncated, so its digest doesn't give away the whole state.
+
+use crate::merkle_damgard::{Core, Endian, MerkleDamgard, Untruncated};
+
+/// Size of the blocks the compression function takes, in bytes.
+pub const BLOCK_SIZE: usize = 128;
+
+/// Size of a SHA-512 digest in bytes.
+pub const DIGEST_SIZE: usize = 64;
+
+/// Size of a SHA-384 digest in bytes.
+pub const SHA384_DIGEST_SIZE: usize = 48;
+
+/// The SHA-512 state before any input, from FIPS 180-4 section 5.3.5.
+pub const INITIAL_STATE: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The SHA-384 state before any input, from FIPS 180-4 section 5.3.4.
+pub const SHA384_INITIAL_STATE: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+/// The round constants: the first 64 bits of the fractional parts of the
+/// cube roots of the first 80 primes.
+const K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+/// The SHA-512 compression function, with big-endian 64-bit words and a
+/// 128-bit length.
+#[derive(Debug, Clone)]
+pub struct Sha512Core;
+
+impl Core for Sha512Core {
+    type Word = u64;
+    type State = [u64; 8];
+    type Len = u128;
+    type Digest = [u8; DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Big;
+    const INITIAL_STATE: [u64; 8] = INITIAL_STATE;
+
+    fn compress(state: &mut [u64; 8], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+impl Untruncated for Sha512Core {}
+
+/// A SHA-512 hash in progress.
+pub type Sha512 = MerkleDamgard<Sha512Core>;
+
+/// SHA-512 from a different initial state, with the digest truncated to 48
+/// bytes.
+#[derive(Debug, Clone)]
+pub struct Sha384Core;
+
+impl Core for Sha384Core {
+    type Word = u64;
+    type State = [u64; 8];
+    type Len = u128;
+    type Digest = [u8; SHA384_DIGEST_SIZE];
+
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_SIZE: usize = SHA384_DIGEST_SIZE;
+    const ENDIAN: Endian = Endian::Big;
+    const INITIAL_STATE: [u64; 8] = SHA384_INITIAL_STATE;
+
+    fn compress(state: &mut [u64; 8], block: &[u8]) {
+        compress(state, block.try_into().unwrap())
+    }
+}
+
+/// A SHA-384 hash in progress.
+pub type Sha384 = MerkleDamgard<Sha384Core>;
+
+/// Hash `data` with SHA-512 in one go.
+pub fn sha512(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Sha512::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Hash `data` with SHA-384 in one go.
+pub fn sha384(data: &[u8]) -> [u8; SHA384_DIGEST_SIZE] {
+    let mut h = Sha384::new();
+    h.update(data);
+    h.finalize()
+}
+
+/// Mix one block into the state, as in FIPS 180-4 section 6.4.2.
+fn compress(state: &mut [u64; 8], block: &[u8; BLOCK_SIZE]) {
+    let mut w = [0u64; 80];
+    for (w, b) in w.iter_mut().zip(block.chunks_exact(8)) {
+        *w = u64::from_be_bytes(b.try_into().unwrap());
+    }
+    for t in 16..80 {
+        let s0 = w[t - 15].rotate_right(1) ^ w[t - 15].rotate_right(8) ^ (w[t - 15] >> 7);
+        let s1 = w[t - 2].rotate_right(19) ^ w[t - 2].rotate_right(61) ^ (w[t - 2] >> 6);
+        w[t] = w[t - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[t - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (&k, &w) in K.iter().zip(&w) {
+        let big_s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(big_s1)
+            .wrapping_add(ch)
+            .wrapping_add(k)
+            .wrapping_add(w);
+        let big_s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = big_s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{bytes_to_hex, hex_to_bytes};
+
+    const ABC_896: &str = "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+        hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    /// The NIST examples for FIPS 180, plus the empty message.
+    #[test]
+    fn fips180_vectors() {
+        for (input, sha384_hex, sha512_hex) in [
+            (
+                "",
+                "38b060a751ac96384cd9327eb1b1e36a21fdb71114be0743\
+                4c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+                "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+                47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+            ),
+            (
+                "abc",
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163\
+                1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                ABC_896,
+                "09330c33f71147e83d192fc782cd1b4753111b173b3b05d2\
+                2fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039",
+                "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+                501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha384(input.as_bytes())), sha384_hex);
+            assert_eq!(bytes_to_hex(&sha512(input.as_bytes())), sha512_hex);
+        }
+    }
+
+    #[test]
+    fn million_a() {
+        let mut h384 = Sha384::new();
+        let mut h512 = Sha512::new();
+        for _ in 0..1000 {
+            h384.update(&[b'a'; 1000]);
+            h512.update(&[b'a'; 1000]);
+        }
+        assert_eq!(
+            bytes_to_hex(&h384.finalize()),
+            "9d0e1809716474cb086e834e310a4a1ced149e9c00f24852\
+            7972cec5704c2a5b07b8b3dc38ecc4ebae97ddd87f3d8985"
+        );
+        assert_eq!(
+            bytes_to_hex(&h512.finalize()),
+            "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973eb\
+            de0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
+        );
+    }
+
+    /// A sample of the byte-oriented short messages from the NIST CAVP test
+    /// vectors, SHA384ShortMsg.rsp and SHA512ShortMsg.rsp, from
+    /// <https://csrc.nist.gov/projects/cryptographic-algorithm-validation-program/secure-hashing>.
+    #[test]
+    fn cavp_short_msg() {
+        for (msg, expected) in [
+            (
+                "c5",
+                "b52b72da75d0666379e20f9b4a79c33a329a01f06a2fb786\
+                5c9062a28c1de860ba432edfd86b4cb1cb8a75b46076e3b1",
+            ),
+            (
+                "6ece",
+                "53d4773da50d8be4145d8f3a7098ff3691a554a29ae6f652\
+                cc7121eb8bc96fd2210e06ae2fa2a36c4b3b3497341e70f0",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha384(&hex_to_bytes(msg))), expected, "{msg}");
+        }
+        for (msg, expected) in [
+            (
+                "21",
+                "3831a6a6155e509dee59a7f451eb35324d8f8f2df6e3708894740f98fdee2388\
+                9f4de5adb0c5010dfb555cda77c8ab5dc902094c52de3278f35a75ebc25f093a",
+            ),
+            (
+                "9083",
+                "55586ebba48768aeb323655ab6f4298fc9f670964fc2e5f2731e34dfa4b0c09e\
+                6e1e12e3d7286b3145c61c2047fb1a2a1297f36da64160b31fa4c8c2cddd2fb4",
+            ),
+            (
+                "0a55db",
+                "7952585e5330cb247d72bae696fc8a6b0f7d0804577e347d99bc1b11e52f3849\
+                85a428449382306a89261ae143c2f3fb613804ab20b42dc097e5bf4a96ef919b",
+            ),
+        ] {
+            assert_eq!(bytes_to_hex(&sha512(&hex_to_bytes(msg))), expected, "{msg}");
+        }
+    }
+
+    /// The first long message from each of the NIST CAVP test vectors,
+    /// SHA384LongMsg.rsp and SHA512LongMsg.rsp, from the same page. They are
+    /// hashed both at once and in small pieces that straddle the
+    /// 128-byte blocks.
+    #[test]
+    fn cavp_long_msg() {
+        let msg = hex_to_bytes(
+            "62c6a169b9be02b3d7b471a964fc0bcc72b480d26aecb2ed460b7f50016ddaf0\
+            4c51218783f3aadfdff5a04ded030d7b3fb7376b61ba30b90e2da921a4470740\
+            d63fb99fa16cc8ed81abaf8ce4016e50df81da832070372c24a80890aa3a26fa\
+            675710b8fb718266249d496f313c55d0bada101f8f56eeccee4345a8f98f60a3\
+            6662cfda794900d12f9414fcbdfdeb85388a814996b47e24d5c8086e7a8edcc5\
+            3d299d0d033e6bb60c58b83d6e8b57f6c258d6081dd10eb942fdf8ec157ec3e7\
+            5371235a8196eb9d22b1de3a2d30c2abbe0db7650cf6c7159bacbe29b3a93c92\
+            100508",
+        );
+        let expected = "0730e184e7795575569f87030260bb8e54498e0e5d096b18285e988d245b6f34\
+            86d1f2447d5f85bcbe59d5689fc49425";
+        assert_eq!(bytes_to_hex(&sha384(&msg)), expected);
+        let mut h = Sha384::new();
+        for piece in msg.chunks(7) {
+            h.update(piece);
+        }
+        assert_eq!(bytes_to_hex(&h.finalize()), expected);
+
+        let msg = hex_to_bytes(
+            "4f05600950664d5190a2ebc29c9edb89c20079a4d3e6bc3b27d75e34e2fa3d02\
+            768502bd69790078598d5fcf3d6779bfed1284bbe5ad72fb456015181d9587d6\
+            e864c940564eaafb4f2fead4346ea09b6877d9340f6b82eb1515880872213da3\
+            ad88feba9f4f13817a71d6f90a1a17c43a15c038d988b5b29edffe2d6a062813\
+            cedbe852cde302b3e33b696846d2a8e36bd680efcc6cd3f9e9a4c1ae8cac10cc\
+            5244d131677140399176ed46700019a004a163806f7fa467fc4e17b4617bbd76\
+            41aaff7ff56396ba8c08a8be100b33a20b5daf134a2aefa5e1c3496770dcf6ba\
+            a4f7bb",
+        );
+        let expected = "a9db490c708cc72548d78635aa7da79bb253f945d710e5cb677a474efc7c65a2\
+            aab45bc7ca1113c8ce0f3c32e1399de9c459535e8816521ab714b2a6cd200525";
+        assert_eq!(bytes_to_hex(&sha512(&msg)), expected);
+        let mut h = Sha512::new();
+        for piece in msg.chunks(7) {
+            h.update(piece);
+        }
+        assert_eq!(bytes_to_hex(&h.finalize()), expected);
+    }
+
+    #[test]
+    fn padding_fills_to_a_block() {
+        assert_eq!(Sha512::padding(0).len(), 128);
+        assert_eq!(Sha512::padding(111).len(), 17);
+        assert_eq!(Sha512::padding(112).len(), 144);
+        assert_eq!(&Sha512::padding(3)[122..], &[0, 0, 24]);
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_one_shot(data: Vec<u8>, split: usize) {
+            let (a, b) = data.split_at(split % (data.len() + 1));
+            let mut h = Sha512::new();
+            h.update(a);
+            h.update(b);
+            assert_eq!(h.finalize(), sha512(&data));
+        }
+
+        #[test]
+        fn resume_from_digest(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Sha512::padding(msg.len() as u128);
+            let mut h = Sha512::from_state(
+                Sha512::state_from_digest(&sha512(&msg)),
+                (msg.len() + pad.len()) as u128,
+            );
+            h.update(&more);
+            assert_eq!(h.finalize(), sha512(&[&msg[..], &pad, &more].concat()));
+        }
+
+        #[test]
+        fn sha384_resume_from_state(msg: Vec<u8>, more: Vec<u8>) {
+            let pad = Sha512::padding(msg.len() as u128);
+            let mut h = Sha384::new();
+            h.update(&msg);
+            h.update(&pad);
+            let mut resumed = Sha384::from_state(h.state(), h.len());
+            resumed.update(&more);
+            assert_eq!(resumed.finalize(), sha384(&[&msg[..], &pad, &more].concat()));
+        }
+    }
+}
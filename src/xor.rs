@@ -61,28 +61,18 @@ pub fn guess_key_size(ct: &[u8]) -> Vec<usize> {
 }
 
 /// Guess the single-byte key that decodes English text.
+///
+/// Returns the score and the key, or None if no key makes the text
+/// printable.
 pub fn guess_single_byte_key(ct: &[u8]) -> Option<(u32, u8)> {
-    let (best_score, best_key, best_cand) = (0..0xff)
+    let (best_score, best_key) = (0..=u8::MAX)
         .map(|key| {
             let cand: Vec<u8> = ct.iter().map(|c| c ^ key).collect();
-            (score_english(&cand), key, cand)
+            (score_english(&cand), key)
         })
-        // .inspect(|(score, key_byte, cand)| {
-        //     if cand.is_ascii() {
-        //         println!("{score:7} {key_byte:#02x} {}", bytes_to_lossy_ascii(&cand))
-        //     }
-        // })
         .max()
         .unwrap();
-    if best_score == 0 {
-        None
-    } else {
-        println!(
-            "{best_score:7} {best_key:#02x} {}",
-            bytes_to_lossy_ascii(&best_cand)
-        );
-        Some((best_score, best_key))
-    }
+    (best_score > 0).then_some((best_score, best_key))
 }
 
 /// Guess the key of length n>1 that best decodes English text.
@@ -123,3 +113,15 @@ pub fn break_repeating_xor(ct: &[u8]) -> (Key, String) {
     assert!(clear.is_ascii());
     (key, String::from_utf8_lossy(&clear).to_string())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Every key byte is tried, up to and including 0xff.
+    #[test]
+    fn single_byte_key_ff() {
+        let ct = repeating_key_xor(b"Cooking MC's like a pound of bacon", &Key::byte(0xff));
+        assert_eq!(guess_single_byte_key(&ct).map(|(_, k)| k), Some(0xff));
+    }
+}
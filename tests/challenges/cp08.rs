@@ -4,10 +4,15 @@
 
 use std::fs::read_to_string;
 
+use cryptopals::aes::{encrypt_aes_ecb, seal_aes_siv, Key, SivKey};
 use cryptopals::detect::detect_aes_ecb;
 use cryptopals::hex_to_bytes;
 use cryptopals::strs::bytes_to_lossy_ascii;
 
+use crate::seed::replayable_rng;
+
+use rand::prelude::*;
+
 #[test]
 fn challenge_08() {
     let input = read_to_string("input/8.txt").unwrap();
@@ -24,3 +29,22 @@ fn challenge_08() {
     }
     assert_eq!(gotcha, "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a");
 }
+
+/// SIV is deterministic like ECB, but it doesn't show which blocks within a
+/// message are equal, so the ECB detector finds nothing.
+///
+/// It does still reveal when the whole message and associated data repeat.
+#[test]
+fn siv_does_not_leak_repeated_blocks() {
+    let plain = b"YELLOW SUBMARINE".repeat(4);
+
+    let mut rng = replayable_rng();
+    let ecb_key = Key::random_from(&mut rng);
+    assert!(detect_aes_ecb(&encrypt_aes_ecb(&plain, &ecb_key)));
+
+    let siv_key = SivKey::from_slice(&rng.gen::<[u8; 32]>());
+    let sealed = seal_aes_siv(&plain, &[], &siv_key);
+    assert!(!detect_aes_ecb(&sealed));
+    assert_eq!(seal_aes_siv(&plain, &[], &siv_key), sealed);
+    assert_ne!(seal_aes_siv(&plain, &[b"another nonce"], &siv_key), sealed);
+}
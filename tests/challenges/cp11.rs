@@ -2,17 +2,21 @@
 //!
 //! From <https://cryptopals.com/sets/2/challenges/11>.
 
+use std::cell::RefCell;
+
 use proptest::prelude::*;
-use rand::Rng;
+use rand::prelude::*;
 
-use cryptopals::aes::{encrypt_aes_cbc, encrypt_aes_ecb, random_iv, Key};
+use cryptopals::aes::{encrypt_aes_cbc, encrypt_aes_ecb, random_iv_from, Key};
+use cryptopals::attacks::fingerprint::{fingerprint, Mode};
 use cryptopals::detect::detect_aes_ecb;
 use cryptopals::pkcs7;
 
 /// Returns plaintext encrypted with a random key, and randomly choosing
 /// AES-ECB or AES-CBC, and with some random bytes before and after.
-fn encryption_oracle(plain: &[u8], use_ecb: bool) -> Vec<u8> {
-    let mut rnd = rand::thread_rng();
+///
+/// All the randomness comes from `rnd`.
+fn encryption_oracle<R: Rng + ?Sized>(plain: &[u8], use_ecb: bool, rnd: &mut R) -> Vec<u8> {
     let mut extended = Vec::new();
     for _ in 0..rnd.gen_range(5..=10) {
         extended.push(rnd.gen());
@@ -21,12 +25,12 @@ fn encryption_oracle(plain: &[u8], use_ecb: bool) -> Vec<u8> {
     for _ in 0..rnd.gen_range(5..=10) {
         extended.push(rnd.gen());
     }
-    let key = Key::random();
+    let key = Key::random_from(rnd);
     let padded = pkcs7::pad(&extended, 16);
     if use_ecb {
         encrypt_aes_ecb(&padded, &key)
     } else {
-        let iv = random_iv();
+        let iv = random_iv_from(rnd);
         encrypt_aes_cbc(&padded, &iv, &key)
     }
 }
@@ -34,11 +38,25 @@ fn encryption_oracle(plain: &[u8], use_ecb: bool) -> Vec<u8> {
 proptest! {
     /// Detect whether the encryption oracle is using ECB or CBC.
     #[test]
-    fn challenge_11(use_ecb: bool) {
+    fn challenge_11(use_ecb: bool, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
         let plain = [b'-'; 64];
         for _ in 0..64 {
-            let ct = encryption_oracle(&plain, use_ecb);
+            let ct = encryption_oracle(&plain, use_ecb, &mut rng);
             assert_eq!(detect_aes_ecb(&ct), use_ecb);
         }
     }
+
+    /// The fingerprinter finds the mode without a hand-chosen probe, and
+    /// more besides.
+    #[test]
+    fn fingerprint_oracle(use_ecb: bool, seed: u64) {
+        let rng = RefCell::new(StdRng::seed_from_u64(seed));
+        let oracle = |plain: &[u8]| encryption_oracle(plain, use_ecb, &mut *rng.borrow_mut());
+        let fp = fingerprint(&oracle).unwrap();
+        assert_eq!(fp.mode, if use_ecb { Mode::Ecb } else { Mode::Cbc });
+        assert_eq!(fp.block_size, Some(16));
+        assert!(!fp.deterministic);
+        assert_eq!(fp.prefix_len, None);
+    }
 }
@@ -2,20 +2,31 @@
 //!
 //! <https://cryptopals.com/sets/2/challenges/16>
 
-use cryptopals::aes::{self, decrypt_aes_cbc, encrypt_aes_cbc};
+use cryptopals::aes::{self, decrypt_aes_cbc, encrypt_aes_cbc, open_aes_gcm, seal_aes_gcm};
+use cryptopals::attacks::bitflip::{cbc_bitflip, ctr_bitflip, find_layout};
+use cryptopals::hmac::{hmac, Hmac};
 use cryptopals::pkcs7::{pad, unpad};
+use cryptopals::random::random_bytes_from;
+use cryptopals::sha256::Sha256;
 use cryptopals::strs::bytes_to_lossy_ascii;
+use cryptopals::Error;
+
+use crate::seed::replayable_rng;
 
 /// Encrypt a string including quoted user-supplied data with a prefix and
 /// suffix.
 fn encrypt_cookie(userdata: &str, secret_key: &aes::Key, iv: &[u8]) -> Vec<u8> {
+    let padded = pad(&cookie_plaintext(userdata), aes::BLOCKSIZE);
+    encrypt_aes_cbc(&padded, iv, secret_key)
+}
+
+/// Quote the userdata and surround it with the fixed prefix and suffix.
+fn cookie_plaintext(userdata: &str) -> Vec<u8> {
     let mut plain: Vec<u8> = b"comment1=cooking%20MCs;userdata=".to_vec();
-    // todo!("escape userdata");
     let userdata = userdata.replace(';', "%3b").replace('=', "%3d");
     plain.extend_from_slice(userdata.as_bytes());
     plain.extend_from_slice(b";comment2=%20like%20a%20pound%20of%20bacon".as_slice());
-    let padded = pad(&plain, aes::BLOCKSIZE);
-    encrypt_aes_cbc(&padded, iv, secret_key)
+    plain
 }
 
 /// Take an encrypted cookie and say whether the contents indicate that the user
@@ -46,34 +57,83 @@ fn challenge_16() {
     // something like "XadminYtrueX" and then do a bitflip on those two bytes to make
     // them ';' and '='.
     //
-    // To do this we need to know the alignment. There are 32 bytes before the
-    // userdata starts, so no need to align the attack. We could insert
-    // one sacrifical block, and then one target block containing "XadminYtrueX"
-    // preceded by 4 bytes more of padding.
-    let key = aes::Key::random();
-    let iv = aes::random_iv();
-    let userdata = "0123456789abcdef,,,,XadminYtrueX";
-    let mut ct = encrypt_cookie(userdata, &key, &iv);
-    // Within the target block, we want to flip X to ';' at offset 4 and offset 15.
-    // And the target starts at offset 32.
-    ct[32 + 4] ^= b'X' ^ b';';
-    ct[32 + 15] ^= b'X' ^ b';';
-    ct[32 + 10] ^= b'Y' ^ b'=';
+    // To do this we need to know the alignment. We find the length of the
+    // prefix by watching which ciphertext blocks change as we vary the input,
+    // then insert enough filler to finish its last block, one sacrificial
+    // block, and then the target block starting with "XadminYtrueX".
+    let mut rng = replayable_rng();
+    let key = aes::Key::random_from(&mut rng);
+    let iv = aes::random_iv_from(&mut rng);
+    let oracle = |input: &[u8]| encrypt_cookie(&String::from_utf8_lossy(input), &key, &iv);
+    let layout = find_layout(&oracle, b"XadminYtrueX", b',').unwrap();
+    assert_eq!(layout.offset, 48);
+    let ct = oracle(&layout.input);
+    let flip = cbc_bitflip(&ct, &iv, layout.offset, b"XadminYtrueX", b";admin=true;").unwrap();
+    // Only the sacrificial block is scrambled, and the IV is untouched.
+    assert_eq!(flip.scrambled, [2]);
+    assert_eq!(flip.iv, iv);
+    let ct = flip.ct;
     assert!(is_admin(&ct, &key, &iv));
 }
 
 #[test]
 fn not_admin_by_default() {
-    let key = aes::Key::random();
-    let iv = aes::random_iv();
+    let mut rng = replayable_rng();
+    let key = aes::Key::random_from(&mut rng);
+    let iv = aes::random_iv_from(&mut rng);
     let ct = encrypt_cookie("mbp", &key, &iv);
     assert!(!is_admin(&ct, &key, &iv));
 }
 
 #[test]
 fn direct_injection_is_blocked_by_quoting() {
-    let key = aes::Key::random();
-    let iv = aes::random_iv();
+    let mut rng = replayable_rng();
+    let key = aes::Key::random_from(&mut rng);
+    let iv = aes::random_iv_from(&mut rng);
     let ct = encrypt_cookie(";admin=true", &key, &iv);
     assert!(!is_admin(&ct, &key, &iv));
 }
+
+/// With authenticated encryption the same kind of bitflip is detected.
+///
+/// GCM is a stream cipher underneath, so flipping ciphertext bits would flip
+/// exactly the same plaintext bits, without even needing a sacrificial
+/// block. But the tag no longer matches, so the server rejects the cookie.
+#[test]
+fn gcm_rejects_bitflipped_cookie() {
+    let mut rng = replayable_rng();
+    let key = aes::Key::random_from(&mut rng);
+    let nonce = [0x42; 12];
+    let userdata = "XadminYtrueX";
+    let sealed = seal_aes_gcm(&cookie_plaintext(userdata), &nonce, b"", &key);
+    let sealed = ctr_bitflip(&sealed, 32, b"XadminYtrueX", b";admin=true;").unwrap();
+    assert!(open_aes_gcm(&sealed, &nonce, b"", &key).is_err());
+
+    // Without the tag check, the flip would have worked.
+    let mut ctr =
+        aes::Ctr::new(&key, aes::CounterLayout::BigEndian32, &nonce).with_initial_counter(2);
+    let mut plain = sealed[..sealed.len() - aes::gcm::TAG_LEN].to_vec();
+    ctr.apply_keystream(&mut plain);
+    assert!(String::from_utf8_lossy(&plain).contains(";admin=true;"));
+}
+
+/// Adding a HMAC over the IV and ciphertext (encrypt-then-MAC) catches the
+/// same flip, even though the CBC underneath is unchanged.
+#[test]
+fn hmac_rejects_bitflipped_cookie() {
+    let mut rng = replayable_rng();
+    let key = aes::Key::random_from(&mut rng);
+    let mac_key = random_bytes_from(&mut rng, 32);
+    let iv = aes::random_iv_from(&mut rng);
+    let oracle = |input: &[u8]| encrypt_cookie(&String::from_utf8_lossy(input), &key, &iv);
+    let layout = find_layout(&oracle, b"XadminYtrueX", b',').unwrap();
+    let ct = oracle(&layout.input);
+    let tag = hmac::<Sha256>(&mac_key, &[&iv[..], &ct].concat());
+    let flip = cbc_bitflip(&ct, &iv, layout.offset, b"XadminYtrueX", b";admin=true;").unwrap();
+    // Without the MAC check, the flip would have worked.
+    assert!(is_admin(&flip.ct, &key, &iv));
+    let mut h = Hmac::<Sha256>::new(&mac_key);
+    h.update(&flip.iv);
+    h.update(&flip.ct);
+    assert_eq!(h.verify(&tag), Err(Error::TagMismatch));
+}
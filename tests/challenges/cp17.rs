@@ -4,12 +4,20 @@
 
 use eyre::{Context, Result};
 
-use cryptopals::aes::{self, encrypt_aes_cbc, random_iv, Key, BLOCKSIZE};
+use cryptopals::aes::{
+    self, encrypt_aes_cbc, encrypt_aes_cbc_cts, random_iv_from, try_decrypt_aes_cbc_cts,
+    CiphertextStealing, Key,
+};
+use cryptopals::attacks::padding_oracle;
 use cryptopals::base64::base64_to_bytes;
+use cryptopals::block::{BlockMode, Cbc};
 use cryptopals::hex::bytes_to_hex;
-use cryptopals::pkcs7::{self, unpad};
+use cryptopals::pkcs7;
+use cryptopals::toy::ToyCipher;
 
-use rand::prelude::SliceRandom;
+use crate::seed::replayable_rng;
+
+use rand::prelude::*;
 
 const TARGETS: &[&str] = &[
     "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
@@ -24,69 +32,33 @@ const TARGETS: &[&str] = &[
     "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
 ];
 
-fn select_and_encrypt(key: &aes::Key) -> (Vec<u8>, [u8; 16]) {
-    let iv = random_iv();
-    let mut rng = rand::thread_rng();
-    let plain = base64_to_bytes(TARGETS.choose(&mut rng).unwrap());
+/// Encrypt one of the targets, chosen by `rng`, under a random IV.
+fn select_and_encrypt<R: Rng + ?Sized>(key: &aes::Key, rng: &mut R) -> (Vec<u8>, [u8; 16]) {
+    let iv = random_iv_from(rng);
+    let plain = base64_to_bytes(TARGETS.choose(rng).unwrap());
     let padded = pkcs7::pad(plain.as_slice(), aes::BLOCKSIZE);
     let ct = encrypt_aes_cbc(&padded, &iv, key);
     (ct, iv)
 }
 
 /// Returns true if the padding is valid
+///
+/// Ciphertext or IVs of the wrong length are rejected rather than panicking.
 fn check_padding(ct: &[u8], iv: &[u8], key: &aes::Key) -> bool {
-    let plain = aes::decrypt_aes_cbc(ct, iv, key);
-    let is_padded = pkcs7::unpad(&plain).is_some();
+    let Ok(plain) = aes::try_decrypt_aes_cbc(ct, iv, key) else {
+        return false;
+    };
+    let is_padded = pkcs7::try_unpad(&plain).is_ok();
     if is_padded {
         println!("plaintext {} is_padded={is_padded}", bytes_to_hex(&plain));
     }
     is_padded
 }
 
-/// Recover the plaintext using a padding oracle attack.
-fn padding_attack<P>(ct: &[u8], iv: &[u8], padding_oracle: P) -> Vec<u8>
-where
-    P: Fn(&[u8], &[u8]) -> bool,
-{
-    let mut last_ct_blk: &[u8] = iv;
-    // Work on just one block of the cyphertext at a time.
-    assert!(ct.len() >= BLOCKSIZE);
-    assert!(ct.len() & (BLOCKSIZE - 1) == 0);
-    let mut recovered = vec![0; ct.len()];
-    for (iblk, blk) in ct.chunks_exact(BLOCKSIZE).enumerate() {
-        let mut miv = vec![0; BLOCKSIZE];
-        'i: for p in 1u8..=16 {
-            // padding value to insert
-            let i = 16 - (p as usize); // position to insert it
-            for j in (i + 1)..=15 {
-                // Update later bytes to all match a run of [p; i].
-                miv[j] = recovered[iblk * BLOCKSIZE + j] ^ (p as u8) ^ last_ct_blk[j];
-            }
-            for b in 0..=255u8 {
-                miv[i] = b;
-                if padding_oracle(blk, &miv) {
-                    // TODO: Taking the first value might not be right if it is close to 0:
-                    // perhaps the correct next value is near 0xff??
-                    let r = b ^ p ^ last_ct_blk[i];
-                    let ioverall = iblk * BLOCKSIZE + i;
-                    println!(
-                        "found valid padding for byte {ioverall} b {r} {:?}",
-                        (r as char)
-                    );
-                    recovered[ioverall] = r;
-                    continue 'i;
-                }
-            }
-            unreachable!("no acceptable mutation found for byte {i} in block {iblk}");
-        }
-        last_ct_blk = blk;
-    }
-    recovered
-}
-
 #[test]
 fn construct_padding_using_iv() {
-    let key = Key::random();
+    let mut rng = replayable_rng();
+    let key = Key::random_from(&mut rng);
     let plain = [0u8; 16];
     let mut iv = [0u8; 16];
     let ct = encrypt_aes_cbc(&plain, &iv, &key);
@@ -102,24 +74,29 @@ fn construct_padding_using_iv() {
     assert!(check_padding(&ct, &iv, &key));
 }
 
+#[test]
+fn malformed_ciphertext_is_rejected() {
+    let mut rng = replayable_rng();
+    let key = Key::random_from(&mut rng);
+    let iv = random_iv_from(&mut rng);
+    assert!(!check_padding(&[0; 15], &iv, &key));
+    assert!(!check_padding(&[0; 16], &iv[..8], &key));
+}
+
 #[test]
 /// Recover all the strings
 fn challenge_17() -> Result<()> {
     let n = TARGETS.len();
     let mut all: Vec<String> = vec![String::new(); n];
-    let key = Key::random();
+    let mut rng = replayable_rng();
+    let key = Key::random_from(&mut rng);
     let mut got = 0;
     while got < n {
-        let (ct, iv) = select_and_encrypt(&key);
-        let recovered = padding_attack(&ct, &iv, |ct, iv| check_padding(ct, iv, &key));
-        let recovered = unpad(recovered.as_ref())
-            .unwrap_or_else(|| {
-                panic!(
-                    "recovered bytes do not seem to be padded: {}",
-                    bytes_to_hex(&recovered.as_ref())
-                )
-            })
-            .to_owned();
+        let (ct, iv) = select_and_encrypt(&key, &mut rng);
+        let oracle = |ct: &[u8], iv: &[u8]| check_padding(ct, iv, &key);
+        let recovered = padding_oracle::attack(&ct, &iv, &oracle)?;
+        println!("recovered in {} queries", recovered.queries);
+        let recovered = recovered.plaintext()?.to_owned();
         let recovered = String::from_utf8_lossy(&recovered);
         println!("recovered: {:?}", recovered);
         let (prefix, message) = recovered.split_at(6);
@@ -148,23 +125,19 @@ fn recover_one_random_string() -> Result<()> {
         "000003Cooking MC's like a pound of bacon",
         "000004Burning 'em, if you ain't quick and nimble",
         "000005I go crazy when I hear a cymbal",
-        "000006nd a high hat with a souped up tempo",
+        "000006And a high hat with a souped up tempo",
         "000007I'm on a roll, it's time to go solo",
         // Strangely the last two lines do seem to be missing some letters,
         // which can be confirmed from the base64-obscured plaintext.
         "000008ollin' in my five point oh",
         "000009ith my rag-top down so my hair can blow",
     ];
-    let key = Key::random();
-    let (ct, iv) = select_and_encrypt(&key);
-    let recovered = padding_attack(&ct, &iv, |ct, iv| check_padding(ct, iv, &key));
-    let recovered = unpad(recovered.as_ref())
-        .unwrap_or_else(|| {
-            panic!(
-                "recovered bytes do not seem to be padded: {}",
-                bytes_to_hex(&recovered.as_ref())
-            )
-        })
+    let mut rng = replayable_rng();
+    let key = Key::random_from(&mut rng);
+    let (ct, iv) = select_and_encrypt(&key, &mut rng);
+    let oracle = |ct: &[u8], iv: &[u8]| check_padding(ct, iv, &key);
+    let recovered = padding_oracle::attack(&ct, &iv, &oracle)?
+        .plaintext()?
         .to_owned();
     let recovered = String::from_utf8(recovered.clone()).with_context(|| {
         format!(
@@ -181,27 +154,76 @@ fn recover_one_random_string() -> Result<()> {
 #[test]
 fn padding_attack_with_known_text() {
     let plain = b"0123456789abcdef";
-    let iv = random_iv();
-    let key = Key::random();
+    let mut rng = replayable_rng();
+    let iv = random_iv_from(&mut rng);
+    let key = Key::random_from(&mut rng);
     let ct = encrypt_aes_cbc(plain.as_slice(), &iv, &key);
-    let recovered = padding_attack(&ct, &iv, |ct, iv| check_padding(ct, iv, &key));
-    assert_eq!(&recovered, plain);
+    let oracle = |ct: &[u8], iv: &[u8]| check_padding(ct, iv, &key);
+    let recovered = padding_oracle::attack(&ct, &iv, &oracle).unwrap();
+    assert_eq!(&recovered.padded, plain);
+}
+
+/// With ciphertext stealing there's no padding to check: every ciphertext of
+/// at least one block decrypts to something. So an oracle built on the real
+/// decryption accepts any tampering, and only tells the attacker whether the
+/// length is valid, which they knew already.
+#[test]
+fn ciphertext_stealing_has_no_padding_oracle() {
+    let plain = b"Cooking MC's like a pound of bacon";
+    let mut rng = replayable_rng();
+    let iv = random_iv_from(&mut rng);
+    let key = Key::random_from(&mut rng);
+    let ct = encrypt_aes_cbc_cts(plain, &iv, CiphertextStealing::Cs3, &key);
+    let oracle = |ct: &[u8], iv: &[u8]| {
+        try_decrypt_aes_cbc_cts(ct, iv, CiphertextStealing::Cs3, &key).is_ok()
+    };
+    assert!(oracle(&ct, &iv));
+    for i in 0..ct.len() {
+        for bit in 0..8 {
+            let mut tampered = ct.clone();
+            tampered[i] ^= 1 << bit;
+            assert!(oracle(&tampered, &iv));
+        }
+    }
+    for i in 0..iv.len() {
+        let mut tampered = iv;
+        tampered[i] ^= 0x01;
+        assert!(oracle(&ct, &tampered));
+    }
+    for len in 0..ct.len() {
+        assert_eq!(oracle(&ct[..len], &iv), len >= aes::BLOCKSIZE);
+    }
+}
+
+/// The attack works against a cipher with a different block size.
+#[test]
+fn padding_attack_on_toy_cipher() {
+    let toy = ToyCipher::new(0x5eed_5eed_5eed_5eed);
+    let iv = b"8bytesIV";
+    let plain = b"Cooking MC's like a pound of bacon";
+    let cbc = Cbc::new(iv);
+    let ct = cbc.encrypt(&toy, &pkcs7::pad(plain, 8));
+    let oracle = |ct: &[u8], iv: &[u8]| pkcs7::unpad(&Cbc::new(iv).decrypt(&toy, ct)).is_some();
+    let recovered = padding_oracle::attack(&ct, iv, &oracle).unwrap();
+    assert_eq!(recovered.plaintext().unwrap(), plain);
 }
 
 #[test]
 fn basic_roundtrip_is_padded() {
+    let mut rng = replayable_rng();
     for _ in 0..99 {
-        let key = aes::Key::random();
-        let (ct, iv) = select_and_encrypt(&key);
+        let key = aes::Key::random_from(&mut rng);
+        let (ct, iv) = select_and_encrypt(&key, &mut rng);
         assert!(check_padding(&ct, &iv, &key));
     }
 }
 
 #[test]
 fn last_block_is_always_padded() {
+    let mut rng = replayable_rng();
     for _ in 0..99 {
-        let key = aes::Key::random();
-        let (ct, iv) = select_and_encrypt(&key);
+        let key = aes::Key::random_from(&mut rng);
+        let (ct, iv) = select_and_encrypt(&key, &mut rng);
         let o = (ct.len() - 1) & !0xff;
         assert!(check_padding(&ct[o..], &iv, &key,));
     }
@@ -0,0 +1,30 @@
+//! Implement CTR, the stream cipher mode.
+//!
+//! <https://cryptopals.com/sets/3/challenges/18>
+
+use cryptopals::aes::{self, decrypt_aes_ctr, encrypt_aes_ctr, CounterLayout};
+use cryptopals::base64_to_bytes;
+
+#[test]
+fn challenge_18() {
+    let ct =
+        base64_to_bytes("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==");
+    let key = aes::Key::from_slice(b"YELLOW SUBMARINE");
+    let nonce = 0u64.to_le_bytes();
+    let plain = decrypt_aes_ctr(&ct, &nonce, CounterLayout::LittleEndian64, &key);
+    let plain = String::from_utf8(plain).expect("plaintext is ascii");
+    println!("{plain}");
+    assert_eq!(
+        plain,
+        "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+    );
+    assert_eq!(
+        encrypt_aes_ctr(
+            plain.as_bytes(),
+            &nonce,
+            CounterLayout::LittleEndian64,
+            &key
+        ),
+        ct
+    );
+}
@@ -0,0 +1,32 @@
+//! Crack an MT19937 seed.
+//!
+//! <https://cryptopals.com/sets/3/challenges/22>
+
+use rand::prelude::*;
+
+use cryptopals::attacks::mt19937::recover_time_seed;
+use cryptopals::random::{Clock, FakeClock, Mt19937};
+
+use crate::seed::replayable_rng;
+
+/// Wait a while, seed with the time, wait a while more, and return the
+/// first output.
+///
+/// The waits are on a fake clock so the test doesn't take half an hour.
+fn routine<R: Rng + ?Sized>(clock: &FakeClock, rng: &mut R) -> (u32, u32) {
+    clock.advance(rng.gen_range(40..=1000));
+    let seed = clock.now() as u32;
+    let mut mt = Mt19937::from_time(clock);
+    clock.advance(rng.gen_range(40..=1000));
+    (mt.next_u32(), seed)
+}
+
+#[test]
+fn challenge_22() {
+    let clock = FakeClock::new(1_700_000_000);
+    let (output, seed) = routine(&clock, &mut replayable_rng());
+    assert_eq!(recover_time_seed(output, &clock, 2000), Some(seed));
+    // Outside the window it's not found.
+    clock.advance(5000);
+    assert_eq!(recover_time_seed(output, &clock, 2000), None);
+}
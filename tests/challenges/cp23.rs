@@ -0,0 +1,18 @@
+//! Clone an MT19937 RNG from its output.
+//!
+//! <https://cryptopals.com/sets/3/challenges/23>
+
+use rand::prelude::*;
+
+use cryptopals::attacks::mt19937::clone_mt;
+use cryptopals::random::{Mt19937, MT_N};
+
+#[test]
+fn challenge_23() {
+    let mut mt = Mt19937::new(random());
+    let outputs: Vec<u32> = (0..MT_N).map(|_| mt.next_u32()).collect();
+    let mut clone = clone_mt(&outputs).unwrap();
+    for _ in 0..1000 {
+        assert_eq!(clone.next_u32(), mt.next_u32());
+    }
+}
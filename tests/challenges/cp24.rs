@@ -0,0 +1,44 @@
+//! Create the MT19937 stream cipher and break it.
+//!
+//! <https://cryptopals.com/sets/3/challenges/24>
+
+use rand::prelude::*;
+
+use cryptopals::attacks::mt19937::{recover_mt_stream_seed, recover_time_seed};
+use cryptopals::random::{mt_stream_xor, random_bytes_from, Clock, FakeClock, Mt19937};
+
+use crate::seed::replayable_rng;
+
+#[test]
+fn challenge_24() {
+    let mut rng = replayable_rng();
+    let key: u16 = rng.gen();
+    let len = rng.gen_range(5..=20);
+    let mut plain = random_bytes_from(&mut rng, len);
+    plain.extend_from_slice(&[b'A'; 14]);
+    let ct = mt_stream_xor(&plain, key);
+    assert_eq!(recover_mt_stream_seed(&ct, &[b'A'; 14]), Some(key));
+    assert_eq!(recover_mt_stream_seed(&ct, &[]), None);
+}
+
+/// A password reset token from an MT19937 seeded with the current time.
+fn reset_token(clock: &dyn Clock) -> [u8; 16] {
+    let mut token = [0; 16];
+    Mt19937::from_time(clock).fill_bytes(&mut token);
+    token
+}
+
+/// The first four bytes of the token are the generator's first output, so
+/// we can tell if it was seeded with a recent time.
+#[test]
+fn detect_time_seeded_token() {
+    let clock = FakeClock::new(1_700_000_000);
+    let token = reset_token(&clock);
+    clock.advance(300);
+    let first = u32::from_le_bytes(token[..4].try_into().unwrap());
+    assert!(recover_time_seed(first, &clock, 3600).is_some());
+
+    let random_token: [u8; 16] = random();
+    let first = u32::from_le_bytes(random_token[..4].try_into().unwrap());
+    assert_eq!(recover_time_seed(first, &clock, 3600), None);
+}
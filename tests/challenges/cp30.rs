@@ -0,0 +1,39 @@
+//! Break a MD4 keyed MAC using length extension.
+//!
+//! <https://cryptopals.com/sets/4/challenges/30>
+
+use rand::Rng;
+
+use cryptopals::attacks::length_extension::forge_verified;
+use cryptopals::hash::Hash;
+use cryptopals::md4::Md4;
+use cryptopals::random::random_bytes_from;
+
+use crate::seed::replayable_rng;
+
+const MESSAGE: &[u8] =
+    b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+/// A MAC of `key || message`.
+fn secret_prefix_mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    Md4::digest(&[key, message].concat())
+}
+
+/// True if the message has an `admin=true` field.
+fn is_admin(message: &[u8]) -> bool {
+    message.split(|&b| b == b';').any(|f| f == b"admin=true")
+}
+
+#[test]
+fn challenge_30() {
+    let mut rng = replayable_rng();
+    let len = rng.gen_range(1..=64);
+    let key = random_bytes_from(&mut rng, len);
+    let mac = secret_prefix_mac(&key, MESSAGE);
+    let verify = |message: &[u8], mac: &[u8]| secret_prefix_mac(&key, message) == mac;
+    let forgery = forge_verified::<Md4, _>(&mac, MESSAGE, 0..=64, b";admin=true", &verify).unwrap();
+    assert_eq!(forgery.secret_len, key.len());
+    assert!(verify(&forgery.message, &forgery.mac));
+    assert!(is_admin(&forgery.message));
+    assert!(!is_admin(MESSAGE));
+}
@@ -15,3 +15,11 @@ mod cp14;
 mod cp15;
 mod cp16;
 mod cp17;
+mod cp18;
+mod cp19;
+mod cp22;
+mod cp23;
+mod cp24;
+mod cp29;
+mod cp30;
+mod seed;
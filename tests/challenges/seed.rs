@@ -0,0 +1,23 @@
+//! Seeding the randomness in the challenge tests, so that a failure can be
+//! replayed.
+
+use rand::prelude::*;
+
+/// The environment variable that [`replayable_rng`] takes its seed from.
+pub const SEED_VAR: &str = "CRYPTOPALS_SEED";
+
+/// Make an RNG for a test, seeded from `$CRYPTOPALS_SEED` if it's set, or
+/// else at random.
+///
+/// The seed is printed, so a test that fails can be run again with the same
+/// randomness.
+pub fn replayable_rng() -> StdRng {
+    let seed = match std::env::var(SEED_VAR) {
+        Ok(s) => s
+            .parse()
+            .unwrap_or_else(|_| panic!("{SEED_VAR} is not a u64: {s:?}")),
+        Err(_) => thread_rng().gen(),
+    };
+    println!("{SEED_VAR}={seed}");
+    StdRng::seed_from_u64(seed)
+}